@@ -4,17 +4,32 @@ use std::f64::consts::PI;
 use std::rc::Rc;
 
 use gtk4::prelude::*;
-use gtk4::{DrawingArea, GestureDrag, GestureClick};
+use gtk4::{DrawingArea, EventControllerMotion, GestureDrag, GestureClick};
 
+use crate::annotate::eyedropper;
+use crate::annotate::history::{Command, History};
 use crate::annotate::shapes::*;
 use crate::annotate::tools::*;
 use crate::config::Config;
 
+/// Number of recently used colors kept for the toolbar
+const RECENT_COLORS_MAX: usize = 8;
+
+/// Largest share of the screenshot's width or height a pasted image covers
+/// before it is scaled down
+const PASTE_MAX_FRACTION: f64 = 0.8;
+
+/// Shape list snapshot taken when an eraser stroke begins
+struct EraseSession {
+    before: Vec<Shape>,
+    changed: bool,
+}
+
 /// Shared mutable state for the annotation canvas
 pub struct CanvasState {
     pub surface: ImageSurface,
     pub shapes: Vec<Shape>,
-    pub undo_stack: Vec<Shape>,
+    pub history: History,
     pub active_draw: ActiveDraw,
     pub current_tool: ToolKind,
     pub current_color: Color,
@@ -22,6 +37,24 @@ pub struct CanvasState {
     pub font_size: f64,
     pub blur_block_size: u32,
     pub pending_text_position: Option<(f64, f64)>,
+    /// Pointer position over the canvas, tracked while the eyedropper is active
+    pub pointer: Option<(f64, f64)>,
+    /// Color chosen outside the color button (eyedropper, palette, shortcut),
+    /// waiting for the toolbar to pick it up
+    pub pending_color: Option<Color>,
+    /// Preset swatches from the config
+    pub palette: Vec<Color>,
+    /// Most recently used colors, newest first
+    pub recent_colors: Vec<Color>,
+    /// Set when `recent_colors` changed and the toolbar row needs rebuilding
+    pub recent_changed: bool,
+    /// Eraser splits freehand strokes instead of removing them whole
+    pub eraser_splits: bool,
+    /// Index in `shapes` of the image layer showing move/resize handles
+    pub selected_image: Option<usize>,
+    erase_session: Option<EraseSession>,
+    /// Shape list before the current move or resize of an image
+    transform_before: Option<Vec<Shape>>,
 }
 
 impl CanvasState {
@@ -29,34 +62,238 @@ impl CanvasState {
         Self {
             surface,
             shapes: Vec::new(),
-            undo_stack: Vec::new(),
+            history: History::default(),
             active_draw: ActiveDraw::None,
             current_tool: ToolKind::Arrow,
-            current_color: Color::from_hex(&config.annotation.default_color),
+            current_color: config.annotation.color(),
             line_width: config.annotation.line_width,
             font_size: config.annotation.font_size,
             blur_block_size: config.annotation.blur_block_size,
             pending_text_position: None,
+            pointer: None,
+            pending_color: None,
+            palette: config.annotation.palette_colors(),
+            recent_colors: Vec::new(),
+            recent_changed: false,
+            eraser_splits: false,
+            selected_image: None,
+            erase_session: None,
+            transform_before: None,
         }
     }
 
     pub fn undo(&mut self) {
-        if let Some(shape) = self.shapes.pop() {
-            self.undo_stack.push(shape);
-        }
+        self.selected_image = None;
+        self.history.undo(&mut self.shapes);
     }
 
     pub fn redo(&mut self) {
-        if let Some(shape) = self.undo_stack.pop() {
-            self.shapes.push(shape);
+        self.selected_image = None;
+        self.history.redo(&mut self.shapes);
+    }
+
+    /// Append a finished shape and record it in the history
+    pub fn push_shape(&mut self, shape: Shape) {
+        if !matches!(shape, Shape::Blur(_) | Shape::Image(_)) {
+            self.remember_color();
+        }
+        self.history.execute(Command::Add(shape), &mut self.shapes);
+    }
+
+    /// Remove every annotation as a single undoable step
+    pub fn clear_annotations(&mut self) {
+        if self.shapes.is_empty() {
+            return;
+        }
+        self.selected_image = None;
+        let command = Command::Replace {
+            before: self.shapes.clone(),
+            after: Vec::new(),
+        };
+        self.history.execute(command, &mut self.shapes);
+    }
+
+    /// Radius of the eraser, scaled with the current line width
+    pub fn eraser_radius(&self) -> f64 {
+        (self.line_width * 2.0).max(6.0)
+    }
+
+    /// Start an eraser stroke. All removals until `end_erase` form one undo step.
+    pub fn begin_erase(&mut self) {
+        self.selected_image = None;
+        self.erase_session = Some(EraseSession {
+            before: self.shapes.clone(),
+            changed: false,
+        });
+    }
+
+    /// Erase along the pointer path from `from` to `to`
+    pub fn erase_segment(&mut self, from: (f64, f64), to: (f64, f64)) {
+        let radius = self.eraser_radius();
+        let len = ((to.0 - from.0).powi(2) + (to.1 - from.1).powi(2)).sqrt();
+        // Sample at half-radius spacing so fast strokes don't skip thin shapes
+        let steps = (len / (radius / 2.0)).ceil().max(1.0) as usize;
+        for i in 0..=steps {
+            let t = i as f64 / steps as f64;
+            let p = (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+            if self.erase_at(p, radius) {
+                if let Some(session) = self.erase_session.as_mut() {
+                    session.changed = true;
+                }
+            }
+        }
+    }
+
+    /// Remove (or split) every shape touched by the eraser at `p`.
+    /// Returns true if the shape list changed.
+    fn erase_at(&mut self, p: (f64, f64), radius: f64) -> bool {
+        let mut changed = false;
+        let mut i = self.shapes.len();
+        while i > 0 {
+            i -= 1;
+            if self.eraser_splits {
+                if let Shape::Freehand(stroke) = &self.shapes[i] {
+                    if let Some(pieces) = stroke.split_at(p, radius) {
+                        self.shapes
+                            .splice(i..=i, pieces.into_iter().map(Shape::Freehand));
+                        changed = true;
+                    }
+                    continue;
+                }
+            }
+            if self.shapes[i].hit_test(p, radius) {
+                self.shapes.remove(i);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Finish an eraser stroke, recording it in the history if anything was erased
+    pub fn end_erase(&mut self) {
+        if let Some(session) = self.erase_session.take() {
+            if session.changed {
+                self.history.record(Command::Replace {
+                    before: session.before,
+                    after: self.shapes.clone(),
+                });
+            }
+        }
+    }
+
+    /// Add an image layer centered on the screenshot, scaled down if it would
+    /// cover most of it, and select it
+    pub fn paste_image(&mut self, surface: ImageSurface) {
+        let canvas_w = self.surface.width() as f64;
+        let canvas_h = self.surface.height() as f64;
+        let mut image = ImageShape::new(surface, 0.0, 0.0);
+        let scale = (canvas_w * PASTE_MAX_FRACTION / image.width)
+            .min(canvas_h * PASTE_MAX_FRACTION / image.height)
+            .min(1.0);
+        image.width *= scale;
+        image.height *= scale;
+        image.x = (canvas_w - image.width) / 2.0;
+        image.y = (canvas_h - image.height) / 2.0;
+        self.push_shape(Shape::Image(image));
+        self.selected_image = Some(self.shapes.len() - 1);
+    }
+
+    /// Index of the topmost image layer under `p`
+    pub fn image_at(&self, p: (f64, f64)) -> Option<usize> {
+        self.shapes
+            .iter()
+            .rposition(|shape| matches!(shape, Shape::Image(_)) && shape.hit_test(p, 0.0))
+    }
+
+    fn selected(&self) -> Option<&ImageShape> {
+        match self.shapes.get(self.selected_image?) {
+            Some(Shape::Image(image)) => Some(image),
+            _ => None,
+        }
+    }
+
+    /// Start moving the selected image if `p` is on it, or resizing it if `p` is
+    /// on one of its handles. All changes until `end_transform` form one undo step.
+    pub fn begin_transform(&mut self, p: (f64, f64)) -> Option<ActiveDraw> {
+        let image = self.selected()?;
+        let handle = image.handle_at(p);
+        if handle.is_none() && !image.contains(p) {
+            return None;
+        }
+        self.transform_before = Some(self.shapes.clone());
+        Some(ActiveDraw::Transform { handle, current: p })
+    }
+
+    /// Move the selected image by the pointer motion from `from` to `to`, or
+    /// drag its corner `handle` to `to`
+    pub fn transform_image(&mut self, handle: Option<usize>, from: (f64, f64), to: (f64, f64)) {
+        let Some(index) = self.selected_image else { return };
+        if let Some(Shape::Image(image)) = self.shapes.get_mut(index) {
+            match handle {
+                Some(handle) => image.resize(handle, to),
+                None => {
+                    image.x += to.0 - from.0;
+                    image.y += to.1 - from.1;
+                }
+            }
+        }
+    }
+
+    /// Finish a move or resize, recording it in the history
+    pub fn end_transform(&mut self) {
+        if let Some(before) = self.transform_before.take() {
+            let moved = before.iter().zip(&self.shapes).any(|pair| match pair {
+                (Shape::Image(a), Shape::Image(b)) => {
+                    (a.x, a.y, a.width, a.height) != (b.x, b.y, b.width, b.height)
+                }
+                _ => false,
+            });
+            if moved {
+                self.history.record(Command::Replace {
+                    before,
+                    after: self.shapes.clone(),
+                });
+            }
         }
     }
 
+    /// Sample the screenshot at (x, y) and make it the current color
+    pub fn pick_color(&mut self, x: f64, y: f64) {
+        if let Some(color) = eyedropper::sample_color(&self.surface, x, y) {
+            self.set_color(color);
+        }
+    }
+
+    /// Change the current color and let the toolbar's color button follow
+    pub fn set_color(&mut self, color: Color) {
+        self.current_color = color.clone();
+        self.pending_color = Some(color);
+    }
+
+    /// Select the palette swatch at `index`. Returns false if there is none.
+    pub fn select_palette(&mut self, index: usize) -> bool {
+        match self.palette.get(index).cloned() {
+            Some(color) => {
+                self.set_color(color);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move the current color to the front of the recent colors
+    fn remember_color(&mut self) {
+        let hex = self.current_color.to_hex();
+        self.recent_colors.retain(|c| c.to_hex() != hex);
+        self.recent_colors.insert(0, self.current_color.clone());
+        self.recent_colors.truncate(RECENT_COLORS_MAX);
+        self.recent_changed = true;
+    }
+
     pub fn add_text(&mut self, text: String) {
         if let Some((x, y)) = self.pending_text_position.take() {
             if !text.is_empty() {
-                self.undo_stack.clear();
-                self.shapes.push(Shape::Text(TextShape {
+                self.push_shape(Shape::Text(TextShape {
                     x,
                     y,
                     text,
@@ -156,9 +393,39 @@ pub fn render_shape(cr: &cairo::Context, shape: &Shape, pango_layout: &pango::La
         Shape::Blur(_) => {
             // Blur is rendered separately via render_blur_shape
         }
+        Shape::Image(image) => {
+            let _ = cr.save();
+            cr.translate(image.x, image.y);
+            cr.scale(
+                image.width / image.surface.width().max(1) as f64,
+                image.height / image.surface.height().max(1) as f64,
+            );
+            let _ = cr.set_source_surface(&image.surface, 0.0, 0.0);
+            cr.source().set_filter(cairo::Filter::Good);
+            let _ = cr.paint();
+            let _ = cr.restore();
+        }
     }
 }
 
+/// Outline and resize handles of the selected image. Only shown in the editor.
+fn render_selection(cr: &cairo::Context, image: &ImageShape) {
+    cr.set_source_rgba(0.2, 0.5, 1.0, 0.9);
+    cr.set_line_width(1.0);
+    cr.set_dash(&[4.0, 3.0], 0.0);
+    cr.rectangle(image.x, image.y, image.width, image.height);
+    let _ = cr.stroke();
+    cr.set_dash(&[], 0.0);
+
+    for (x, y) in image.corners() {
+        cr.rectangle(x - HANDLE_SIZE / 2.0, y - HANDLE_SIZE / 2.0, HANDLE_SIZE, HANDLE_SIZE);
+    }
+    cr.set_source_rgb(1.0, 1.0, 1.0);
+    let _ = cr.fill_preserve();
+    cr.set_source_rgba(0.2, 0.5, 1.0, 0.9);
+    let _ = cr.stroke();
+}
+
 /// Render a blur shape as actual pixelation on the canvas
 fn render_blur_shape(cr: &cairo::Context, blur: &BlurShape, base_surface: &ImageSurface) {
     if let Some(pixelated) = crate::annotate::blur::pixelate_region_copy(
@@ -184,6 +451,16 @@ fn render_blur_preview(cr: &cairo::Context, blur: &BlurShape) {
     cr.set_dash(&[], 0.0);
 }
 
+/// Read drawing constraints from the modifier keys held during a drag
+fn draw_modifiers(gesture: &GestureDrag) -> DrawModifiers {
+    let keys = gesture.current_event_state();
+    DrawModifiers {
+        constrain: keys.contains(gdk4::ModifierType::SHIFT_MASK),
+        from_center: keys
+            .intersects(gdk4::ModifierType::CONTROL_MASK | gdk4::ModifierType::ALT_MASK),
+    }
+}
+
 /// Build the annotation DrawingArea with event handlers.
 /// Returns the DrawingArea and a reference to the shared canvas state.
 pub fn build_canvas(
@@ -218,6 +495,10 @@ pub fn build_canvas(
             }
         }
 
+        if let Some(image) = st.selected() {
+            render_selection(cr, image);
+        }
+
         // Render active (in-progress) shape preview
         if let Some(preview) = st.active_draw.to_preview_shape(
             &st.current_color,
@@ -229,6 +510,21 @@ pub fn build_canvas(
                 _ => render_shape(cr, &preview, &layout),
             }
         }
+
+        // Eraser cursor outline
+        if let ActiveDraw::Eraser { current } = st.active_draw {
+            cr.set_source_rgba(0.2, 0.2, 0.2, 0.8);
+            cr.set_line_width(1.0);
+            cr.arc(current.0, current.1, st.eraser_radius(), 0.0, 2.0 * PI);
+            let _ = cr.stroke();
+        }
+
+        // Eyedropper magnifier
+        if st.current_tool == ToolKind::Eyedropper {
+            if let Some((x, y)) = st.pointer {
+                eyedropper::render_magnifier(cr, &st.surface, x, y);
+            }
+        }
     });
 
     // Drag gesture for drawing
@@ -237,10 +533,22 @@ pub fn build_canvas(
     let da_press = drawing_area.clone();
     drag.connect_drag_begin(move |_gesture, x, y| {
         let mut st = state_press.borrow_mut();
+        // Pressing on the selected image moves or resizes it, anywhere else deselects it
+        if let Some(transform) = st.begin_transform((x, y)) {
+            st.active_draw = transform;
+            return;
+        }
+        if st.selected_image.take().is_some() {
+            da_press.queue_draw();
+        }
         if st.current_tool == ToolKind::Text {
             return;
         }
         st.active_draw = ActiveDraw::begin(st.current_tool, x, y);
+        if st.current_tool == ToolKind::Eraser {
+            st.begin_erase();
+            st.erase_segment((x, y), (x, y));
+        }
         da_press.queue_draw();
     });
 
@@ -249,7 +557,14 @@ pub fn build_canvas(
     drag.connect_drag_update(move |gesture, offset_x, offset_y| {
         if let Some((start_x, start_y)) = gesture.start_point() {
             let mut st = state_update.borrow_mut();
-            st.active_draw.update(start_x + offset_x, start_y + offset_y);
+            let pos = (start_x + offset_x, start_y + offset_y);
+            if let ActiveDraw::Eraser { current } = st.active_draw {
+                st.erase_segment(current, pos);
+            }
+            if let ActiveDraw::Transform { handle, current } = st.active_draw {
+                st.transform_image(handle, current, pos);
+            }
+            st.active_draw.update(pos.0, pos.1, draw_modifiers(gesture));
             da_update.queue_draw();
         }
     });
@@ -259,19 +574,27 @@ pub fn build_canvas(
     drag.connect_drag_end(move |gesture, offset_x, offset_y| {
         if let Some((start_x, start_y)) = gesture.start_point() {
             let mut st = state_end.borrow_mut();
-            st.active_draw.update(start_x + offset_x, start_y + offset_y);
+            let pos = (start_x + offset_x, start_y + offset_y);
+            if let ActiveDraw::Eraser { current } = st.active_draw {
+                st.erase_segment(current, pos);
+                st.end_erase();
+            }
+            if let ActiveDraw::Transform { handle, current } = st.active_draw {
+                st.transform_image(handle, current, pos);
+                st.end_transform();
+            }
+            st.active_draw.update(pos.0, pos.1, draw_modifiers(gesture));
             let draw = std::mem::replace(&mut st.active_draw, ActiveDraw::None);
             if let Some(shape) = draw.finish(&st.current_color, st.line_width, st.blur_block_size)
             {
-                st.undo_stack.clear();
-                st.shapes.push(shape);
+                st.push_shape(shape);
             }
             da_end.queue_draw();
         }
     });
     drawing_area.add_controller(drag);
 
-    // Click gesture for text tool
+    // Click gesture for the text tool, eyedropper and image selection
     let click = GestureClick::new();
     let state_click = state.clone();
     let da_click = drawing_area.clone();
@@ -280,10 +603,39 @@ pub fn build_canvas(
         if st.current_tool == ToolKind::Text {
             st.pending_text_position = Some((x, y));
             da_click.queue_draw();
+        } else if st.current_tool == ToolKind::Eyedropper {
+            st.pick_color(x, y);
+            da_click.queue_draw();
+        } else {
+            // Clicking an image layer selects it for moving and resizing
+            let selected = st.image_at((x, y));
+            if selected != st.selected_image {
+                st.selected_image = selected;
+                da_click.queue_draw();
+            }
         }
     });
     drawing_area.add_controller(click);
 
+    // Pointer tracking for the eyedropper magnifier
+    let motion = EventControllerMotion::new();
+    let state_motion = state.clone();
+    let da_motion = drawing_area.clone();
+    motion.connect_motion(move |_ctrl, x, y| {
+        let mut st = state_motion.borrow_mut();
+        if st.current_tool == ToolKind::Eyedropper {
+            st.pointer = Some((x, y));
+            da_motion.queue_draw();
+        }
+    });
+    let state_leave = state.clone();
+    let da_leave = drawing_area.clone();
+    motion.connect_leave(move |_ctrl| {
+        state_leave.borrow_mut().pointer = None;
+        da_leave.queue_draw();
+    });
+    drawing_area.add_controller(motion);
+
     (drawing_area, state)
 }
 
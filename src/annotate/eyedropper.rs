@@ -0,0 +1,92 @@
+use cairo::ImageSurface;
+
+use crate::annotate::shapes::Color;
+
+/// Side length of the magnifier in screen pixels
+const MAGNIFIER_SIZE: f64 = 121.0;
+/// Number of source pixels shown across the magnifier (odd, so one is centered)
+const MAGNIFIER_PIXELS: i32 = 11;
+/// Offset of the magnifier from the pointer
+const MAGNIFIER_OFFSET: f64 = 20.0;
+
+/// Read the color of a single pixel of `surface`.
+/// Returns None if the position lies outside the image.
+pub fn sample_color(surface: &ImageSurface, x: f64, y: f64) -> Option<Color> {
+    let px = x.floor() as i32;
+    let py = y.floor() as i32;
+    if px < 0 || py < 0 || px >= surface.width() || py >= surface.height() {
+        return None;
+    }
+
+    // Copy the pixel into a 1x1 surface so the source surface needn't be borrowed mutably
+    let mut pixel = ImageSurface::create(cairo::Format::ARgb32, 1, 1).ok()?;
+    let cr = cairo::Context::new(&pixel).ok()?;
+    cr.set_source_surface(surface, -px as f64, -py as f64).ok()?;
+    cr.paint().ok()?;
+    drop(cr);
+    pixel.flush();
+
+    // Cairo's ARGB32 is little-endian BGRA with premultiplied alpha
+    let data = pixel.data().ok()?;
+    let a = data[3] as f64;
+    if a == 0.0 {
+        return Some(Color {
+            r: 0.0,
+            g: 0.0,
+            b: 0.0,
+            a: 0.0,
+        });
+    }
+    Some(Color {
+        r: data[2] as f64 / a,
+        g: data[1] as f64 / a,
+        b: data[0] as f64 / a,
+        a: a / 255.0,
+    })
+}
+
+/// Draw a magnified view of the pixels around (x, y), with the sampled pixel
+/// outlined and its hex value printed underneath.
+pub fn render_magnifier(cr: &cairo::Context, surface: &ImageSurface, x: f64, y: f64) {
+    let scale = MAGNIFIER_SIZE / MAGNIFIER_PIXELS as f64;
+    let half = (MAGNIFIER_PIXELS / 2) as f64;
+    let mx = x + MAGNIFIER_OFFSET;
+    let my = y + MAGNIFIER_OFFSET;
+
+    // Zoomed pixels, nearest-neighbour so individual pixels stay crisp
+    let _ = cr.save();
+    cr.rectangle(mx, my, MAGNIFIER_SIZE, MAGNIFIER_SIZE);
+    cr.clip();
+    cr.set_source_rgb(0.0, 0.0, 0.0);
+    let _ = cr.paint();
+    cr.translate(mx, my);
+    cr.scale(scale, scale);
+    let _ = cr.set_source_surface(surface, -(x.floor() - half), -(y.floor() - half));
+    cr.source().set_filter(cairo::Filter::Nearest);
+    let _ = cr.paint();
+    let _ = cr.restore();
+
+    // Outline the sampled pixel
+    cr.set_source_rgba(1.0, 1.0, 1.0, 0.9);
+    cr.set_line_width(1.0);
+    cr.rectangle(mx + half * scale, my + half * scale, scale, scale);
+    let _ = cr.stroke();
+
+    // Border
+    cr.set_source_rgba(0.0, 0.0, 0.0, 0.8);
+    cr.set_line_width(2.0);
+    cr.rectangle(mx, my, MAGNIFIER_SIZE, MAGNIFIER_SIZE);
+    let _ = cr.stroke();
+
+    // Hex value label
+    if let Some(color) = sample_color(surface, x, y) {
+        let label_y = my + MAGNIFIER_SIZE;
+        cr.set_source_rgba(0.0, 0.0, 0.0, 0.8);
+        cr.rectangle(mx, label_y, MAGNIFIER_SIZE, 20.0);
+        let _ = cr.fill();
+        cr.set_source_rgba(1.0, 1.0, 1.0, 0.9);
+        cr.set_font_size(13.0);
+        cr.move_to(mx + 6.0, label_y + 15.0);
+        let _ = cr.show_text(&color.to_hex());
+    }
+}
@@ -0,0 +1,76 @@
+use crate::annotate::shapes::Shape;
+
+/// A reversible mutation of the annotation list
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// Append a shape to the end of the list
+    Add(Shape),
+    /// Swap the whole list, e.g. an eraser stroke or "Clear all"
+    Replace {
+        before: Vec<Shape>,
+        after: Vec<Shape>,
+    },
+}
+
+impl Command {
+    fn apply(&self, shapes: &mut Vec<Shape>) {
+        match self {
+            Command::Add(shape) => shapes.push(shape.clone()),
+            Command::Replace { after, .. } => *shapes = after.clone(),
+        }
+    }
+
+    fn revert(&self, shapes: &mut Vec<Shape>) {
+        match self {
+            Command::Add(_) => {
+                shapes.pop();
+            }
+            Command::Replace { before, .. } => *shapes = before.clone(),
+        }
+    }
+}
+
+/// Unlimited undo/redo history of editor commands
+#[derive(Debug, Default)]
+pub struct History {
+    done: Vec<Command>,
+    undone: Vec<Command>,
+}
+
+impl History {
+    /// Apply a command to `shapes` and record it. Discards anything that could be redone.
+    pub fn execute(&mut self, command: Command, shapes: &mut Vec<Shape>) {
+        command.apply(shapes);
+        self.record(command);
+    }
+
+    /// Record a command whose effect has already been applied
+    pub fn record(&mut self, command: Command) {
+        self.undone.clear();
+        self.done.push(command);
+    }
+
+    /// Revert the most recent command. Returns false if there was nothing to undo.
+    pub fn undo(&mut self, shapes: &mut Vec<Shape>) -> bool {
+        match self.done.pop() {
+            Some(command) => {
+                command.revert(shapes);
+                self.undone.push(command);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Re-apply the most recently undone command. Returns false if there was nothing to redo.
+    pub fn redo(&mut self, shapes: &mut Vec<Shape>) -> bool {
+        match self.undone.pop() {
+            Some(command) => {
+                command.apply(shapes);
+                self.done.push(command);
+                true
+            }
+            None => false,
+        }
+    }
+}
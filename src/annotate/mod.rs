@@ -1,5 +1,8 @@
 pub mod blur;
 pub mod canvas;
+pub mod color;
+pub mod eyedropper;
+pub mod history;
 pub mod shapes;
 pub mod toolbar;
 pub mod tools;
@@ -1,28 +1,11 @@
-/// Color as RGBA floats (0.0..1.0)
-#[derive(Debug, Clone)]
-pub struct Color {
-    pub r: f64,
-    pub g: f64,
-    pub b: f64,
-    pub a: f64,
-}
-
-impl Color {
-    pub fn from_hex(hex: &str) -> Self {
-        let hex = hex.trim_start_matches('#');
-        let r = u8::from_str_radix(&hex[0..2], 16).unwrap_or(255) as f64 / 255.0;
-        let g = u8::from_str_radix(&hex[2..4], 16).unwrap_or(0) as f64 / 255.0;
-        let b = u8::from_str_radix(&hex[4..6], 16).unwrap_or(0) as f64 / 255.0;
-        Self { r, g, b, a: 1.0 }
-    }
+use cairo::ImageSurface;
+use serde::Serialize;
 
-    pub fn apply(&self, cr: &cairo::Context) {
-        cr.set_source_rgba(self.r, self.g, self.b, self.a);
-    }
-}
+pub use crate::annotate::color::Color;
 
 /// All annotation shape types
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
 pub enum Shape {
     Arrow(ArrowShape),
     Line(LineShape),
@@ -32,9 +15,10 @@ pub enum Shape {
     Freehand(FreehandShape),
     Highlight(HighlightShape),
     Blur(BlurShape),
+    Image(ImageShape),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ArrowShape {
     pub start: (f64, f64),
     pub end: (f64, f64),
@@ -42,7 +26,7 @@ pub struct ArrowShape {
     pub line_width: f64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct LineShape {
     pub start: (f64, f64),
     pub end: (f64, f64),
@@ -50,7 +34,7 @@ pub struct LineShape {
     pub line_width: f64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct RectShape {
     pub x: f64,
     pub y: f64,
@@ -60,7 +44,7 @@ pub struct RectShape {
     pub line_width: f64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct EllipseShape {
     pub cx: f64,
     pub cy: f64,
@@ -70,7 +54,7 @@ pub struct EllipseShape {
     pub line_width: f64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct TextShape {
     pub x: f64,
     pub y: f64,
@@ -79,14 +63,14 @@ pub struct TextShape {
     pub font_size: f64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct FreehandShape {
     pub points: Vec<(f64, f64)>,
     pub color: Color,
     pub line_width: f64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct HighlightShape {
     pub x: f64,
     pub y: f64,
@@ -95,7 +79,7 @@ pub struct HighlightShape {
     pub color: Color,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct BlurShape {
     pub x: f64,
     pub y: f64,
@@ -103,3 +87,235 @@ pub struct BlurShape {
     pub height: f64,
     pub block_size: u32,
 }
+
+/// A pasted image layer, drawn scaled into its box
+#[derive(Debug, Clone, Serialize)]
+pub struct ImageShape {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    #[serde(skip)]
+    pub surface: ImageSurface,
+}
+
+/// Side length of the resize handles on a selected image
+pub const HANDLE_SIZE: f64 = 10.0;
+
+/// Smallest width or height an image can be resized to
+const MIN_IMAGE_SIZE: f64 = 8.0;
+
+/// Approximate glyph advance as a fraction of the font size, used for text hit-testing
+/// where no Pango layout is available.
+const TEXT_ADVANCE_RATIO: f64 = 0.6;
+
+impl Shape {
+    /// Whether a circle of `radius` centred on `p` touches the visible part of this shape.
+    pub fn hit_test(&self, p: (f64, f64), radius: f64) -> bool {
+        match self {
+            Shape::Arrow(a) => {
+                dist_to_segment(p, a.start, a.end) <= radius + a.line_width / 2.0
+            }
+            Shape::Line(l) => dist_to_segment(p, l.start, l.end) <= radius + l.line_width / 2.0,
+            Shape::Rectangle(r) => {
+                let corners = [
+                    (r.x, r.y),
+                    (r.x + r.width, r.y),
+                    (r.x + r.width, r.y + r.height),
+                    (r.x, r.y + r.height),
+                    (r.x, r.y),
+                ];
+                dist_to_polyline(p, &corners) <= radius + r.line_width / 2.0
+            }
+            Shape::Ellipse(e) => {
+                let outline: Vec<(f64, f64)> = (0..=64)
+                    .map(|i| {
+                        let t = i as f64 / 64.0 * 2.0 * std::f64::consts::PI;
+                        (e.cx + e.rx * t.cos(), e.cy + e.ry * t.sin())
+                    })
+                    .collect();
+                dist_to_polyline(p, &outline) <= radius + e.line_width / 2.0
+            }
+            Shape::Text(t) => {
+                let width = t.text.chars().count() as f64 * t.font_size * TEXT_ADVANCE_RATIO;
+                let height = t.font_size * 1.5;
+                rect_contains(p, t.x, t.y, width, height, radius)
+            }
+            Shape::Freehand(f) => {
+                dist_to_polyline(p, &f.points) <= radius + f.line_width / 2.0
+            }
+            Shape::Highlight(h) => rect_contains(p, h.x, h.y, h.width, h.height, radius),
+            Shape::Blur(b) => rect_contains(p, b.x, b.y, b.width, b.height, radius),
+            Shape::Image(i) => rect_contains(p, i.x, i.y, i.width, i.height, radius),
+        }
+    }
+}
+
+impl ImageShape {
+    /// An image at its natural size with its top-left corner at (x, y)
+    pub fn new(surface: ImageSurface, x: f64, y: f64) -> Self {
+        Self {
+            x,
+            y,
+            width: surface.width() as f64,
+            height: surface.height() as f64,
+            surface,
+        }
+    }
+
+    /// Corners clockwise from the top-left
+    pub fn corners(&self) -> [(f64, f64); 4] {
+        [
+            (self.x, self.y),
+            (self.x + self.width, self.y),
+            (self.x + self.width, self.y + self.height),
+            (self.x, self.y + self.height),
+        ]
+    }
+
+    pub fn contains(&self, p: (f64, f64)) -> bool {
+        rect_contains(p, self.x, self.y, self.width, self.height, 0.0)
+    }
+
+    /// Index into `corners()` of the resize handle under `p`
+    pub fn handle_at(&self, p: (f64, f64)) -> Option<usize> {
+        let reach = HANDLE_SIZE / 2.0 + 2.0;
+        self.corners()
+            .iter()
+            .position(|c| (p.0 - c.0).abs() <= reach && (p.1 - c.1).abs() <= reach)
+    }
+
+    /// Drag corner `handle` to `p`, keeping the opposite corner in place and
+    /// the image's aspect ratio
+    pub fn resize(&mut self, handle: usize, p: (f64, f64)) {
+        let aspect = self.surface.width() as f64 / self.surface.height().max(1) as f64;
+        let anchor = self.corners()[(handle + 2) % 4];
+        let width = (p.0 - anchor.0)
+            .abs()
+            .max((p.1 - anchor.1).abs() * aspect)
+            .max(MIN_IMAGE_SIZE)
+            .max(MIN_IMAGE_SIZE * aspect);
+        let height = width / aspect;
+        // Handles 0 and 3 are on the left, 0 and 1 at the top
+        self.x = if handle == 0 || handle == 3 { anchor.0 - width } else { anchor.0 };
+        self.y = if handle == 0 || handle == 1 { anchor.1 - height } else { anchor.1 };
+        self.width = width;
+        self.height = height;
+    }
+}
+
+impl FreehandShape {
+    /// Cut away the parts of the stroke within `radius` of `p`, returning the
+    /// remaining runs. Segments are cut where they cross the eraser, so it also
+    /// splits a fast stroke whose points lie on either side of it. Runs with
+    /// fewer than two points are dropped.
+    /// Returns `None` if the eraser did not touch the stroke.
+    pub fn split_at(&self, p: (f64, f64), radius: f64) -> Option<Vec<FreehandShape>> {
+        let reach = radius + self.line_width / 2.0;
+        if dist_to_polyline(p, &self.points) > reach {
+            return None;
+        }
+
+        let mut pieces = Vec::new();
+        let mut run: Vec<(f64, f64)> = Vec::new();
+        for w in self.points.windows(2) {
+            let (a, b) = (w[0], w[1]);
+            let Some((t0, t1)) = circle_overlap(p, reach, a, b) else {
+                if run.is_empty() {
+                    run.push(a);
+                }
+                run.push(b);
+                continue;
+            };
+            // The part before the eraser ends the current run...
+            if t0 > 0.0 {
+                if run.is_empty() {
+                    run.push(a);
+                }
+                run.push(lerp(a, b, t0));
+            }
+            if run.len() > 1 {
+                pieces.push(std::mem::take(&mut run));
+            }
+            run.clear();
+            // ...and the part after it starts the next one
+            if t1 < 1.0 {
+                run.push(lerp(a, b, t1));
+                run.push(b);
+            }
+        }
+        if run.len() > 1 {
+            pieces.push(run);
+        }
+
+        Some(
+            pieces
+                .into_iter()
+                .map(|points| FreehandShape {
+                    points,
+                    color: self.color.clone(),
+                    line_width: self.line_width,
+                })
+                .collect(),
+        )
+    }
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+fn lerp(a: (f64, f64), b: (f64, f64), t: f64) -> (f64, f64) {
+    (a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1))
+}
+
+/// The part of the segment `a`-`b` inside the circle around `p`, as a range of
+/// the segment's parameter within 0..=1; None if it stays outside
+fn circle_overlap(p: (f64, f64), r: f64, a: (f64, f64), b: (f64, f64)) -> Option<(f64, f64)> {
+    let d = (b.0 - a.0, b.1 - a.1);
+    let f = (a.0 - p.0, a.1 - p.1);
+    let qa = d.0 * d.0 + d.1 * d.1;
+    let qb = 2.0 * (f.0 * d.0 + f.1 * d.1);
+    let qc = f.0 * f.0 + f.1 * f.1 - r * r;
+    if qa == 0.0 {
+        return (qc <= 0.0).then_some((0.0, 1.0));
+    }
+    let discriminant = qb * qb - 4.0 * qa * qc;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    let t0 = (-qb - root) / (2.0 * qa);
+    let t1 = (-qb + root) / (2.0 * qa);
+    if t1 < 0.0 || t0 > 1.0 {
+        return None;
+    }
+    Some((t0.max(0.0), t1.min(1.0)))
+}
+
+/// Shortest distance from `p` to the segment `a`-`b`
+fn dist_to_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let dx = b.0 - a.0;
+    let dy = b.1 - a.1;
+    let len_sq = dx * dx + dy * dy;
+    if len_sq == 0.0 {
+        return distance(p, a);
+    }
+    let t = (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len_sq).clamp(0.0, 1.0);
+    distance(p, (a.0 + t * dx, a.1 + t * dy))
+}
+
+fn dist_to_polyline(p: (f64, f64), points: &[(f64, f64)]) -> f64 {
+    match points {
+        [] => f64::INFINITY,
+        [only] => distance(p, *only),
+        _ => points
+            .windows(2)
+            .map(|w| dist_to_segment(p, w[0], w[1]))
+            .fold(f64::INFINITY, f64::min),
+    }
+}
+
+fn rect_contains(p: (f64, f64), x: f64, y: f64, w: f64, h: f64, margin: f64) -> bool {
+    p.0 >= x - margin && p.0 <= x + w + margin && p.1 >= y - margin && p.1 <= y + h + margin
+}
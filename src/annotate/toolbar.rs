@@ -29,6 +29,9 @@ pub fn build_toolbar(
     let draw_btn = Button::with_label("Draw");
     let highlight_btn = Button::with_label("Highlight");
     let blur_btn = Button::with_label("Blur");
+    let eraser_btn = Button::with_label("Erase");
+    let picker_btn = Button::with_label("Pick");
+    picker_btn.set_tooltip_text(Some("Pick a color from the screenshot"));
 
     let tool_buttons = vec![
         (arrow_btn.clone(), ToolKind::Arrow),
@@ -39,6 +42,8 @@ pub fn build_toolbar(
         (draw_btn.clone(), ToolKind::Freehand),
         (highlight_btn.clone(), ToolKind::Highlight),
         (blur_btn.clone(), ToolKind::Blur),
+        (eraser_btn.clone(), ToolKind::Eraser),
+        (picker_btn.clone(), ToolKind::Eyedropper),
     ];
 
     for (btn, tool) in &tool_buttons {
@@ -60,6 +65,15 @@ pub fn build_toolbar(
     // Set initial active button
     arrow_btn.add_css_class("suggested-action");
 
+    // Eraser mode: split freehand strokes instead of removing them whole
+    let split_check = gtk4::CheckButton::with_label("Split");
+    split_check.set_tooltip_text(Some("Eraser splits freehand strokes"));
+    let state_split = state.clone();
+    split_check.connect_toggled(move |check| {
+        state_split.borrow_mut().eraser_splits = check.is_active();
+    });
+    toolbar.append(&split_check);
+
     // Separator
     let sep = gtk4::Separator::new(Orientation::Vertical);
     toolbar.append(&sep);
@@ -92,6 +106,58 @@ pub fn build_toolbar(
     });
     toolbar.append(&color_btn);
 
+    // Reflect colors chosen elsewhere (eyedropper, swatches, shortcuts) in the color button
+    let state_pending = state.clone();
+    color_btn.add_tick_callback(move |btn, _clock| {
+        let pending = state_pending.borrow_mut().pending_color.take();
+        if let Some(c) = pending {
+            btn.set_rgba(&gdk4::RGBA::new(c.r as f32, c.g as f32, c.b as f32, c.a as f32));
+        }
+        glib::ControlFlow::Continue
+    });
+
+    // Palette swatches
+    let palette = state.borrow().palette.clone();
+    for (i, color) in palette.iter().enumerate() {
+        let btn = swatch_button(color);
+        if i < 9 {
+            btn.set_tooltip_text(Some(&format!("{} ({})", color.to_hex(), i + 1)));
+        }
+        let state_swatch = state.clone();
+        btn.connect_clicked(move |_| {
+            state_swatch.borrow_mut().select_palette(i);
+        });
+        toolbar.append(&btn);
+    }
+
+    // Recently used colors, rebuilt whenever a new color is used
+    let recent_box = GtkBox::new(Orientation::Horizontal, 2);
+    let state_recent = state.clone();
+    recent_box.add_tick_callback(move |row, _clock| {
+        let recent = {
+            let mut st = state_recent.borrow_mut();
+            if !st.recent_changed {
+                return glib::ControlFlow::Continue;
+            }
+            st.recent_changed = false;
+            st.recent_colors.clone()
+        };
+        while let Some(child) = row.first_child() {
+            row.remove(&child);
+        }
+        for color in recent {
+            let btn = swatch_button(&color);
+            let state_use = state_recent.clone();
+            btn.connect_clicked(move |_| {
+                state_use.borrow_mut().set_color(color.clone());
+            });
+            row.append(&btn);
+        }
+        glib::ControlFlow::Continue
+    });
+    toolbar.append(&gtk4::Separator::new(Orientation::Vertical));
+    toolbar.append(&recent_box);
+
     // Line width SpinButton
     let lw_adj = gtk4::Adjustment::new(state.borrow().line_width, 1.0, 20.0, 0.5, 1.0, 0.0);
     let lw_spin = gtk4::SpinButton::new(Some(&lw_adj), 0.5, 1);
@@ -141,5 +207,35 @@ pub fn build_toolbar(
     toolbar.append(&undo_btn);
     toolbar.append(&redo_btn);
 
+    // Clear all annotations (undoable)
+    let clear_btn = Button::with_label("Clear");
+    clear_btn.set_tooltip_text(Some("Clear all annotations"));
+    let state_clear = state.clone();
+    let da_clear = drawing_area.clone();
+    clear_btn.connect_clicked(move |_| {
+        state_clear.borrow_mut().clear_annotations();
+        da_clear.queue_draw();
+    });
+    toolbar.append(&clear_btn);
+
     toolbar
 }
+
+/// A small flat button filled with `color`
+fn swatch_button(color: &Color) -> Button {
+    let area = DrawingArea::new();
+    area.set_content_width(16);
+    area.set_content_height(16);
+    let fill = color.clone();
+    area.set_draw_func(move |_da, cr, w, h| {
+        fill.apply(cr);
+        cr.rectangle(0.0, 0.0, w as f64, h as f64);
+        let _ = cr.fill();
+    });
+
+    let btn = Button::new();
+    btn.add_css_class("flat");
+    btn.set_child(Some(&area));
+    btn.set_tooltip_text(Some(&color.to_hex()));
+    btn
+}
@@ -11,6 +11,20 @@ pub enum ToolKind {
     Freehand,
     Highlight,
     Blur,
+    Eraser,
+    Eyedropper,
+}
+
+/// Angle step for constrained lines and arrows (15°)
+const SNAP_ANGLE: f64 = std::f64::consts::PI / 12.0;
+
+/// Keyboard modifiers that constrain an in-progress drawing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DrawModifiers {
+    /// Shift: snap lines/arrows to 15° steps, make boxes square
+    pub constrain: bool,
+    /// Ctrl or Alt: treat the start point as the center of the shape
+    pub from_center: bool,
 }
 
 /// State machine for the currently active drawing interaction
@@ -20,18 +34,22 @@ pub enum ActiveDraw {
     Arrow {
         start: (f64, f64),
         current: (f64, f64),
+        centered: bool,
     },
     Line {
         start: (f64, f64),
         current: (f64, f64),
+        centered: bool,
     },
     Rectangle {
         start: (f64, f64),
         current: (f64, f64),
+        centered: bool,
     },
     Ellipse {
         start: (f64, f64),
         current: (f64, f64),
+        centered: bool,
     },
     Freehand {
         points: Vec<(f64, f64)>,
@@ -39,10 +57,20 @@ pub enum ActiveDraw {
     Highlight {
         start: (f64, f64),
         current: (f64, f64),
+        centered: bool,
     },
     Blur {
         start: (f64, f64),
         current: (f64, f64),
+        centered: bool,
+    },
+    Eraser {
+        current: (f64, f64),
+    },
+    /// Moving the selected image, or resizing it by corner `handle`
+    Transform {
+        handle: Option<usize>,
+        current: (f64, f64),
     },
 }
 
@@ -53,18 +81,22 @@ impl ActiveDraw {
             ToolKind::Arrow => ActiveDraw::Arrow {
                 start: (x, y),
                 current: (x, y),
+                centered: false,
             },
             ToolKind::Line => ActiveDraw::Line {
                 start: (x, y),
                 current: (x, y),
+                centered: false,
             },
             ToolKind::Rectangle => ActiveDraw::Rectangle {
                 start: (x, y),
                 current: (x, y),
+                centered: false,
             },
             ToolKind::Ellipse => ActiveDraw::Ellipse {
                 start: (x, y),
                 current: (x, y),
+                centered: false,
             },
             ToolKind::Freehand => ActiveDraw::Freehand {
                 points: vec![(x, y)],
@@ -72,24 +104,46 @@ impl ActiveDraw {
             ToolKind::Highlight => ActiveDraw::Highlight {
                 start: (x, y),
                 current: (x, y),
+                centered: false,
             },
             ToolKind::Blur => ActiveDraw::Blur {
                 start: (x, y),
                 current: (x, y),
+                centered: false,
             },
-            ToolKind::Text => ActiveDraw::None,
+            ToolKind::Eraser => ActiveDraw::Eraser { current: (x, y) },
+            ToolKind::Text | ToolKind::Eyedropper => ActiveDraw::None,
         }
     }
 
-    /// Update the drawing with a new pointer position
-    pub fn update(&mut self, x: f64, y: f64) {
+    /// Update the drawing with a new pointer position.
+    /// `modifiers` constrain the shape and are re-evaluated on every update,
+    /// so releasing a key mid-drag returns to free drawing.
+    pub fn update(&mut self, x: f64, y: f64, modifiers: DrawModifiers) {
         match self {
-            ActiveDraw::Arrow { current, .. } => *current = (x, y),
-            ActiveDraw::Line { current, .. } => *current = (x, y),
-            ActiveDraw::Rectangle { current, .. } => *current = (x, y),
-            ActiveDraw::Ellipse { current, .. } => *current = (x, y),
-            ActiveDraw::Highlight { current, .. } => *current = (x, y),
-            ActiveDraw::Blur { current, .. } => *current = (x, y),
+            ActiveDraw::Arrow { start, current, centered }
+            | ActiveDraw::Line { start, current, centered } => {
+                *current = if modifiers.constrain {
+                    snap_angle(*start, (x, y))
+                } else {
+                    (x, y)
+                };
+                *centered = modifiers.from_center;
+            }
+            ActiveDraw::Rectangle { start, current, centered }
+            | ActiveDraw::Ellipse { start, current, centered }
+            | ActiveDraw::Highlight { start, current, centered }
+            | ActiveDraw::Blur { start, current, centered } => {
+                *current = if modifiers.constrain {
+                    snap_square(*start, (x, y))
+                } else {
+                    (x, y)
+                };
+                *centered = modifiers.from_center;
+            }
+            ActiveDraw::Eraser { current } | ActiveDraw::Transform { current, .. } => {
+                *current = (x, y)
+            }
             ActiveDraw::Freehand { points } => points.push((x, y)),
             ActiveDraw::None => {}
         }
@@ -98,7 +152,8 @@ impl ActiveDraw {
     /// Finalize the drawing into a shape
     pub fn finish(self, color: &Color, line_width: f64, blur_block_size: u32) -> Option<Shape> {
         match self {
-            ActiveDraw::Arrow { start, current } => {
+            ActiveDraw::Arrow { start, current, centered } => {
+                let (start, current) = endpoints(start, current, centered);
                 if (start.0 - current.0).abs() > 2.0 || (start.1 - current.1).abs() > 2.0 {
                     Some(Shape::Arrow(ArrowShape {
                         start,
@@ -110,7 +165,8 @@ impl ActiveDraw {
                     None
                 }
             }
-            ActiveDraw::Line { start, current } => {
+            ActiveDraw::Line { start, current, centered } => {
+                let (start, current) = endpoints(start, current, centered);
                 if (start.0 - current.0).abs() > 2.0 || (start.1 - current.1).abs() > 2.0 {
                     Some(Shape::Line(LineShape {
                         start,
@@ -122,7 +178,8 @@ impl ActiveDraw {
                     None
                 }
             }
-            ActiveDraw::Rectangle { start, current } => {
+            ActiveDraw::Rectangle { start, current, centered } => {
+                let (start, current) = endpoints(start, current, centered);
                 let x = start.0.min(current.0);
                 let y = start.1.min(current.1);
                 let w = (start.0 - current.0).abs();
@@ -140,7 +197,8 @@ impl ActiveDraw {
                     None
                 }
             }
-            ActiveDraw::Ellipse { start, current } => {
+            ActiveDraw::Ellipse { start, current, centered } => {
+                let (start, current) = endpoints(start, current, centered);
                 let x = start.0.min(current.0);
                 let y = start.1.min(current.1);
                 let w = (start.0 - current.0).abs();
@@ -169,7 +227,8 @@ impl ActiveDraw {
                     None
                 }
             }
-            ActiveDraw::Highlight { start, current } => {
+            ActiveDraw::Highlight { start, current, centered } => {
+                let (start, current) = endpoints(start, current, centered);
                 let x = start.0.min(current.0);
                 let y = start.1.min(current.1);
                 let w = (start.0 - current.0).abs();
@@ -188,7 +247,8 @@ impl ActiveDraw {
                     None
                 }
             }
-            ActiveDraw::Blur { start, current } => {
+            ActiveDraw::Blur { start, current, centered } => {
+                let (start, current) = endpoints(start, current, centered);
                 let x = start.0.min(current.0);
                 let y = start.1.min(current.1);
                 let w = (start.0 - current.0).abs();
@@ -205,26 +265,33 @@ impl ActiveDraw {
                     None
                 }
             }
-            ActiveDraw::None => None,
+            ActiveDraw::Eraser { .. } | ActiveDraw::Transform { .. } | ActiveDraw::None => None,
         }
     }
 
     /// Convert active draw state to a temporary shape for preview rendering
     pub fn to_preview_shape(&self, color: &Color, line_width: f64, blur_block_size: u32) -> Option<Shape> {
         match self {
-            ActiveDraw::Arrow { start, current } => Some(Shape::Arrow(ArrowShape {
-                start: *start,
-                end: *current,
-                color: color.clone(),
-                line_width,
-            })),
-            ActiveDraw::Line { start, current } => Some(Shape::Line(LineShape {
-                start: *start,
-                end: *current,
-                color: color.clone(),
-                line_width,
-            })),
-            ActiveDraw::Rectangle { start, current } => {
+            ActiveDraw::Arrow { start, current, centered } => {
+                let (start, end) = endpoints(*start, *current, *centered);
+                Some(Shape::Arrow(ArrowShape {
+                    start,
+                    end,
+                    color: color.clone(),
+                    line_width,
+                }))
+            }
+            ActiveDraw::Line { start, current, centered } => {
+                let (start, end) = endpoints(*start, *current, *centered);
+                Some(Shape::Line(LineShape {
+                    start,
+                    end,
+                    color: color.clone(),
+                    line_width,
+                }))
+            }
+            ActiveDraw::Rectangle { start, current, centered } => {
+                let (start, current) = endpoints(*start, *current, *centered);
                 let x = start.0.min(current.0);
                 let y = start.1.min(current.1);
                 Some(Shape::Rectangle(RectShape {
@@ -236,7 +303,8 @@ impl ActiveDraw {
                     line_width,
                 }))
             }
-            ActiveDraw::Ellipse { start, current } => {
+            ActiveDraw::Ellipse { start, current, centered } => {
+                let (start, current) = endpoints(*start, *current, *centered);
                 let x = start.0.min(current.0);
                 let y = start.1.min(current.1);
                 let w = (start.0 - current.0).abs();
@@ -255,7 +323,8 @@ impl ActiveDraw {
                 color: color.clone(),
                 line_width,
             })),
-            ActiveDraw::Highlight { start, current } => {
+            ActiveDraw::Highlight { start, current, centered } => {
+                let (start, current) = endpoints(*start, *current, *centered);
                 let x = start.0.min(current.0);
                 let y = start.1.min(current.1);
                 let mut c = color.clone();
@@ -268,7 +337,8 @@ impl ActiveDraw {
                     color: c,
                 }))
             }
-            ActiveDraw::Blur { start, current } => {
+            ActiveDraw::Blur { start, current, centered } => {
+                let (start, current) = endpoints(*start, *current, *centered);
                 let x = start.0.min(current.0);
                 let y = start.1.min(current.1);
                 Some(Shape::Blur(BlurShape {
@@ -279,7 +349,34 @@ impl ActiveDraw {
                     block_size: blur_block_size,
                 }))
             }
-            ActiveDraw::None => None,
+            ActiveDraw::Eraser { .. } | ActiveDraw::Transform { .. } | ActiveDraw::None => None,
         }
     }
 }
+
+/// Resolve the effective start/end points of a two-point drawing.
+/// When drawing from the center, the start point is mirrored through the anchor.
+fn endpoints(start: (f64, f64), current: (f64, f64), centered: bool) -> ((f64, f64), (f64, f64)) {
+    if centered {
+        ((2.0 * start.0 - current.0, 2.0 * start.1 - current.1), current)
+    } else {
+        (start, current)
+    }
+}
+
+/// Snap `p` so the segment from `anchor` lies on the nearest 15° direction
+fn snap_angle(anchor: (f64, f64), p: (f64, f64)) -> (f64, f64) {
+    let dx = p.0 - anchor.0;
+    let dy = p.1 - anchor.1;
+    let len = (dx * dx + dy * dy).sqrt();
+    let angle = (dy.atan2(dx) / SNAP_ANGLE).round() * SNAP_ANGLE;
+    (anchor.0 + len * angle.cos(), anchor.1 + len * angle.sin())
+}
+
+/// Move `p` so the box spanned from `anchor` is square, keeping the drag direction
+fn snap_square(anchor: (f64, f64), p: (f64, f64)) -> (f64, f64) {
+    let dx = p.0 - anchor.0;
+    let dy = p.1 - anchor.1;
+    let side = dx.abs().max(dy.abs());
+    (anchor.0 + side * dx.signum(), anchor.1 + side * dy.signum())
+}
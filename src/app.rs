@@ -1,8 +1,10 @@
 use gtk4::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 use std::sync::mpsc;
 
-use crate::capture;
-use crate::config::Config;
+use crate::capture::{self, CaptureInfo};
+use crate::config::{Config, LoadOptions};
 use crate::output;
 use crate::tray;
 use crate::ui;
@@ -101,19 +103,41 @@ fn do_full_no_edit(app: &gtk4::Application, config: Config, monitor: Option<u32>
         match result {
             Ok(surface) => {
                 let surface = apply_monitor_crop(surface, monitor);
-                match output::file::save_screenshot(&surface, &config) {
-                    Ok(path) => log::info!("Screenshot saved to {}", path.display()),
-                    Err(e) => log::error!("Failed to save screenshot: {}", e),
-                }
+                let info = CaptureInfo::full(monitor);
+                output::hooks::post_capture(&config, &info, &surface);
+                let saved = match output::file::save_screenshot(&surface, &config, &info, &[]) {
+                    Ok(path) => {
+                        log::info!("Screenshot saved to {}", path.display());
+                        Some(path)
+                    }
+                    Err(e) => {
+                        log::error!("Failed to save screenshot: {}", e);
+                        None
+                    }
+                };
                 if config.behavior.copy_to_clipboard {
-                    if let Err(e) = output::clipboard::copy_to_clipboard(&surface) {
+                    if let Err(e) = output::clipboard::copy_to_clipboard(
+                        &surface,
+                        saved.as_deref(),
+                        config.behavior.clipboard_mode,
+                    ) {
                         log::error!("Failed to copy to clipboard: {}", e);
                     }
                 }
+                if let Some(path) = &saved {
+                    output::hooks::post_save(&config, &info, path, surface.width(), surface.height());
+                    output::upload::upload_saved(
+                        &config,
+                        &info,
+                        path,
+                        surface.width(),
+                        surface.height(),
+                    );
+                }
             }
             Err(e) => log::error!("Screenshot capture failed: {}", e),
         }
-        app.quit();
+        output::when_idle(move || app.quit());
     });
 }
 
@@ -124,7 +148,9 @@ fn do_full_edit(app: &gtk4::Application, config: Config, monitor: Option<u32>) {
         match result {
             Ok(surface) => {
                 let surface = apply_monitor_crop(surface, monitor);
-                ui::window::show_editor(&app, surface, config);
+                let info = CaptureInfo::full(monitor);
+                output::hooks::post_capture(&config, &info, &surface);
+                ui::window::show_editor(&app, surface, config, info);
             }
             Err(e) => {
                 log::error!("Screenshot capture failed: {}", e);
@@ -157,24 +183,53 @@ fn do_region_no_edit(app: &gtk4::Application, config: Config) {
                         if let Some((x, y, w, h)) = region {
                             match capture::region::crop_surface(&surface_for_closure, x, y, w, h) {
                                 Ok(cropped) => {
-                                    match output::file::save_screenshot(&cropped, &config_clone) {
+                                    let info = CaptureInfo::region();
+                                    output::hooks::post_capture(&config_clone, &info, &cropped);
+                                    let saved = match output::file::save_screenshot(
+                                        &cropped,
+                                        &config_clone,
+                                        &info,
+                                        &[],
+                                    ) {
                                         Ok(path) => {
-                                            log::info!("Screenshot saved to {}", path.display())
+                                            log::info!("Screenshot saved to {}", path.display());
+                                            Some(path)
                                         }
-                                        Err(e) => log::error!("Failed to save: {}", e),
-                                    }
+                                        Err(e) => {
+                                            log::error!("Failed to save: {}", e);
+                                            None
+                                        }
+                                    };
                                     if config_clone.behavior.copy_to_clipboard {
-                                        if let Err(e) =
-                                            output::clipboard::copy_to_clipboard(&cropped)
-                                        {
+                                        if let Err(e) = output::clipboard::copy_to_clipboard(
+                                            &cropped,
+                                            saved.as_deref(),
+                                            config_clone.behavior.clipboard_mode,
+                                        ) {
                                             log::error!("Failed to copy to clipboard: {}", e);
                                         }
                                     }
+                                    if let Some(path) = &saved {
+                                        output::hooks::post_save(
+                                            &config_clone,
+                                            &info,
+                                            path,
+                                            cropped.width(),
+                                            cropped.height(),
+                                        );
+                                        output::upload::upload_saved(
+                                            &config_clone,
+                                            &info,
+                                            path,
+                                            cropped.width(),
+                                            cropped.height(),
+                                        );
+                                    }
                                 }
                                 Err(e) => log::error!("Failed to crop: {}", e),
                             }
                         }
-                        app_clone.quit();
+                        output::when_idle(move || app_clone.quit());
                     }),
                 );
             }
@@ -209,7 +264,9 @@ fn do_region_edit(app: &gtk4::Application, config: Config) {
                         if let Some((x, y, w, h)) = region {
                             match capture::region::crop_surface(&surface_for_closure, x, y, w, h) {
                                 Ok(cropped) => {
-                                    ui::window::show_editor(&app_clone, cropped, config_clone);
+                                    let info = CaptureInfo::region();
+                                    output::hooks::post_capture(&config_clone, &info, &cropped);
+                                    ui::window::show_editor(&app_clone, cropped, config_clone, info);
                                 }
                                 Err(e) => {
                                     log::error!("Failed to crop: {}", e);
@@ -230,18 +287,187 @@ fn do_region_edit(app: &gtk4::Application, config: Config) {
     });
 }
 
-/// Start in tray mode: system tray icon + poll for actions
-fn do_tray(app: &gtk4::Application, config: Config) {
-    let rx = tray::start_tray();
+/// Open the editor on the image currently on the clipboard
+fn do_edit_clipboard(app: &gtk4::Application, config: Config) {
     let app = app.clone();
+    let open = move |result: Result<cairo::ImageSurface, String>| match result {
+        Ok(surface) => ui::window::show_editor(&app, surface, config, CaptureInfo::clipboard()),
+        Err(e) => {
+            log::error!("Can't edit the clipboard: {}", e);
+            app.quit();
+        }
+    };
+
+    // GTK only sees the Wayland clipboard once it has a focused window, so
+    // ask the compositor directly first
+    match capture::clipboard::read_png() {
+        Ok(surface) => open(Ok(surface)),
+        Err(e) => {
+            log::debug!("Wayland clipboard read failed ({}), asking GTK", e);
+            match gdk4::Display::default() {
+                Some(display) => capture::clipboard::read_image(&display.clipboard(), open),
+                None => open(Err("no display".into())),
+            }
+        }
+    }
+}
+
+/// Show the history gallery. Quits once it and any editor opened from it
+/// are closed and their uploads and hooks are done.
+fn do_gallery(app: &gtk4::Application, config: Config) {
+    app.connect_window_removed(|app, _| {
+        if app.windows().is_empty() {
+            let app = app.clone();
+            output::when_idle(move || app.quit());
+        }
+    });
+    ui::gallery::show_gallery(app, config);
+}
+
+/// Watch the config file and call `on_change` once edits settle.
+/// The returned monitor must be kept alive for as long as changes matter.
+fn watch_config<F>(on_change: F) -> Option<gio::FileMonitor>
+where
+    F: Fn() + 'static,
+{
+    let path = Config::config_path();
+    let file = gio::File::for_path(&path);
+    let monitor = match file.monitor_file(gio::FileMonitorFlags::WATCH_MOVES, gio::Cancellable::NONE) {
+        Ok(m) => m,
+        Err(e) => {
+            log::error!("Cannot watch {} for changes: {}", path.display(), e);
+            return None;
+        }
+    };
+
+    let on_change = Rc::new(on_change);
+    let pending = Rc::new(Cell::new(false));
+    monitor.connect_changed(move |_, _, _, event| {
+        use gio::FileMonitorEvent as Event;
+        if !matches!(
+            event,
+            Event::ChangesDoneHint | Event::Created | Event::Deleted | Event::MovedIn | Event::Renamed
+        ) {
+            return;
+        }
+        // Editors save in several steps; reload once they are done
+        if pending.replace(true) {
+            return;
+        }
+        let pending = pending.clone();
+        let on_change = on_change.clone();
+        glib::timeout_add_local_once(std::time::Duration::from_millis(250), move || {
+            pending.set(false);
+            on_change();
+        });
+    });
+    Some(monitor)
+}
+
+/// Re-read the config file after it changed on disk. An invalid file is
+/// reported and the current settings are kept.
+fn reload_config(
+    config: &RefCell<Config>,
+    options: &RefCell<LoadOptions>,
+    updates: &mpsc::Sender<tray::TrayUpdate>,
+) {
+    let base = match Config::reload() {
+        Ok(c) => c,
+        Err(e) => {
+            log::error!("{}", e);
+            log::error!("Config not reloaded; keeping the previous settings");
+            return;
+        }
+    };
+
+    let mut options = options.borrow_mut();
+    if let Some(name) = &options.profile {
+        if !base.profiles.contains_key(name) {
+            log::warn!("Profile {} no longer exists, switching to the default", name);
+            options.profile = None;
+        }
+    }
+    match base.layered(options.profile.as_deref(), &options.overrides) {
+        Ok(c) => {
+            log::info!("Reloaded {}", Config::config_path().display());
+            let _ = updates.send(tray::TrayUpdate::Profiles {
+                profiles: c.profiles.keys().cloned().collect(),
+                active: options.profile.clone(),
+            });
+            let _ = updates.send(tray::TrayUpdate::Upload { enabled: c.upload.auto });
+            *config.borrow_mut() = c;
+        }
+        Err(e) => {
+            log::error!("{}", e);
+            log::error!("Config not reloaded; keeping the previous settings");
+        }
+    }
+}
+
+/// Start in tray mode: system tray icon + poll for actions.
+/// The config file is watched, so edits apply to the next capture.
+fn do_tray(app: &gtk4::Application, config: Config, options: LoadOptions) {
+    let profiles = config.profiles.keys().cloned().collect();
+    let (rx, updates) = tray::start_tray(profiles, options.profile.clone(), config.upload.auto);
+    let app = app.clone();
+    let config = Rc::new(RefCell::new(config));
+    let options = Rc::new(RefCell::new(options));
+
+    let monitor = {
+        let config = config.clone();
+        let options = options.clone();
+        let updates = updates.clone();
+        watch_config(move || reload_config(&config, &options, &updates))
+    };
 
     glib::timeout_add_local(std::time::Duration::from_millis(100), move || {
+        // Owned here so the file monitor lives as long as the tray
+        let _ = &monitor;
         match rx.try_recv() {
             Ok(tray::TrayAction::CaptureRegion) => {
-                do_region_edit(&app, config.clone());
+                do_region_edit(&app, config.borrow().clone());
             }
             Ok(tray::TrayAction::CaptureFullScreen) => {
-                do_full_edit(&app, config.clone(), None);
+                do_full_edit(&app, config.borrow().clone(), None);
+            }
+            Ok(tray::TrayAction::ShowHistory) => {
+                ui::gallery::show_gallery(&app, config.borrow().clone());
+            }
+            Ok(tray::TrayAction::SelectProfile(profile)) => {
+                let mut options = options.borrow_mut();
+                let previous = std::mem::replace(&mut options.profile, profile);
+                match Config::reload_with(&options) {
+                    Ok(c) => {
+                        log::info!("Switched to profile {:?}", options.profile);
+                        // Profiles may turn uploading on or off
+                        let _ = updates.send(tray::TrayUpdate::Upload { enabled: c.upload.auto });
+                        *config.borrow_mut() = c;
+                    }
+                    Err(e) => {
+                        log::error!("{}", e);
+                        log::error!("Profile not applied; keeping the previous settings");
+                        options.profile = previous;
+                        let _ = updates.send(tray::TrayUpdate::Profiles {
+                            profiles: config.borrow().profiles.keys().cloned().collect(),
+                            active: options.profile.clone(),
+                        });
+                    }
+                }
+            }
+            Ok(tray::TrayAction::SetUpload(enabled)) => {
+                let mut options = options.borrow_mut();
+                let previous = options.overrides.clone();
+                options.set("upload.auto", enabled);
+                match Config::reload_with(&options) {
+                    Ok(c) => *config.borrow_mut() = c,
+                    Err(e) => {
+                        log::error!("{}", e);
+                        log::error!("Upload setting not applied; keeping the previous settings");
+                        options.overrides = previous;
+                        let enabled = config.borrow().upload.auto;
+                        let _ = updates.send(tray::TrayUpdate::Upload { enabled });
+                    }
+                }
             }
             Ok(tray::TrayAction::Quit) => {
                 app.quit();
@@ -264,6 +490,8 @@ pub enum AppAction {
     FullEdit { monitor: Option<u32> },
     RegionNoEdit,
     RegionEdit,
+    EditClipboard,
+    Gallery,
     Tray,
     #[allow(dead_code)]
     ShowConfig,
@@ -271,8 +499,10 @@ pub enum AppAction {
     SetSaveDir(String),
 }
 
-/// Build and run the GtkApplication with the given action.
-pub fn run(action: AppAction) {
+/// Build and run the GtkApplication with the given action and configuration.
+/// `options` are kept so the tray can rebuild the config when switching profiles
+/// or when the file changes.
+pub fn run(action: AppAction, config: Config, options: LoadOptions) {
     let app = gtk4::Application::builder()
         .application_id(APP_ID)
         .flags(gio::ApplicationFlags::FLAGS_NONE)
@@ -285,14 +515,16 @@ pub fn run(action: AppAction) {
         std::mem::forget(guard);
 
         log::debug!("Application activated, dispatching action...");
-        let config = Config::load();
+        let config = config.clone();
 
         match &action_clone {
             AppAction::FullNoEdit { monitor } => do_full_no_edit(app, config, *monitor),
             AppAction::FullEdit { monitor } => do_full_edit(app, config, *monitor),
             AppAction::RegionNoEdit => do_region_no_edit(app, config),
             AppAction::RegionEdit => do_region_edit(app, config),
-            AppAction::Tray => do_tray(app, config),
+            AppAction::EditClipboard => do_edit_clipboard(app, config),
+            AppAction::Gallery => do_gallery(app, config),
+            AppAction::Tray => do_tray(app, config, options.clone()),
             AppAction::ShowConfig | AppAction::SetSaveDir(_) => {
                 unreachable!();
             }
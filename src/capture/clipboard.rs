@@ -0,0 +1,50 @@
+use cairo::ImageSurface;
+use gdk4::prelude::*;
+use std::io::{Cursor, Read};
+
+/// Read a PNG from the Wayland clipboard. Uses the data-control protocol, so it
+/// works before razorshot has a focused window, unlike `read_image`.
+pub fn read_png() -> Result<ImageSurface, String> {
+    use wl_clipboard_rs::paste::{get_contents, ClipboardType, MimeType, Seat};
+
+    let (mut pipe, _) = get_contents(
+        ClipboardType::Regular,
+        Seat::Unspecified,
+        MimeType::Specific("image/png"),
+    )
+    .map_err(|e| e.to_string())?;
+    let mut png_data = Vec::new();
+    pipe.read_to_end(&mut png_data)
+        .map_err(|e| format!("Failed to read clipboard: {e}"))?;
+    ImageSurface::create_from_png(&mut Cursor::new(png_data))
+        .map_err(|e| format!("Failed to decode clipboard PNG: {e}"))
+}
+
+/// Read an image in any format GTK understands from `clipboard` and call
+/// `on_ready` with it
+pub fn read_image<F>(clipboard: &gdk4::Clipboard, on_ready: F)
+where
+    F: FnOnce(Result<ImageSurface, String>) + 'static,
+{
+    clipboard.read_texture_async(None::<&gio::Cancellable>, move |result| {
+        let surface = match result {
+            Ok(Some(texture)) => texture_to_surface(&texture),
+            Ok(None) => Err("No image on the clipboard".to_string()),
+            Err(e) => Err(format!("Failed to read clipboard: {e}")),
+        };
+        on_ready(surface);
+    });
+}
+
+fn texture_to_surface(texture: &gdk4::Texture) -> Result<ImageSurface, String> {
+    let mut surface = ImageSurface::create(cairo::Format::ARgb32, texture.width(), texture.height())
+        .map_err(|e| format!("Surface create failed: {e}"))?;
+    let stride = surface.stride() as usize;
+    {
+        let mut data = surface.data().map_err(|e| format!("Surface data failed: {e}"))?;
+        // GDK's default memory format is cairo's ARGB32 layout
+        texture.download(&mut data, stride);
+    }
+    surface.mark_dirty();
+    Ok(surface)
+}
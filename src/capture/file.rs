@@ -0,0 +1,42 @@
+use cairo::ImageSurface;
+use std::path::Path;
+
+/// Load an image file in any of the export formats, e.g. to edit or copy a
+/// screenshot saved earlier
+pub fn load_image(path: &Path) -> Result<ImageSurface, String> {
+    let is_webp = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("webp"));
+    let image = if is_webp {
+        let data = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        webp::Decoder::new(&data)
+            .decode()
+            .ok_or_else(|| format!("{}: not a valid WebP image", path.display()))?
+            .to_image()
+    } else {
+        image::open(path).map_err(|e| format!("{}: {e}", path.display()))?
+    };
+    rgba_to_surface(&image.to_rgba8())
+}
+
+/// Convert straight RGBA to Cairo's premultiplied BGRA
+fn rgba_to_surface(rgba: &image::RgbaImage) -> Result<ImageSurface, String> {
+    let mut surface =
+        ImageSurface::create(cairo::Format::ARgb32, rgba.width() as i32, rgba.height() as i32)
+            .map_err(|e| format!("Surface create failed: {e}"))?;
+    let stride = surface.stride() as usize;
+    {
+        let mut data = surface.data().map_err(|e| format!("Surface data failed: {e}"))?;
+        for (x, y, pixel) in rgba.enumerate_pixels() {
+            let [r, g, b, a] = pixel.0;
+            let premultiply = |c: u8| ((c as u32 * a as u32 + 127) / 255) as u8;
+            let offset = y as usize * stride + x as usize * 4;
+            data[offset] = premultiply(b);
+            data[offset + 1] = premultiply(g);
+            data[offset + 2] = premultiply(r);
+            data[offset + 3] = a;
+        }
+    }
+    surface.mark_dirty();
+    Ok(surface)
+}
@@ -1,2 +1,72 @@
+pub mod clipboard;
+pub mod file;
 pub mod portal;
 pub mod region;
+
+use chrono::{DateTime, Local};
+
+/// How a screenshot was taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureMode {
+    Region,
+    Full,
+    /// An image taken from the clipboard with `edit --clipboard`
+    Clipboard,
+    /// An existing image file, e.g. one reopened from the history gallery
+    File,
+}
+
+impl CaptureMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            CaptureMode::Region => "region",
+            CaptureMode::Full => "full",
+            CaptureMode::Clipboard => "clipboard",
+            CaptureMode::File => "file",
+        }
+    }
+}
+
+/// Details of a capture, used to fill in the filename template
+#[derive(Debug, Clone, Copy)]
+pub struct CaptureInfo {
+    pub mode: CaptureMode,
+    /// Monitor index for `full --monitor`; None when all monitors were captured
+    pub monitor: Option<u32>,
+    /// When the capture was taken
+    pub time: DateTime<Local>,
+}
+
+impl CaptureInfo {
+    pub fn region() -> Self {
+        Self {
+            mode: CaptureMode::Region,
+            monitor: None,
+            time: Local::now(),
+        }
+    }
+
+    pub fn full(monitor: Option<u32>) -> Self {
+        Self {
+            mode: CaptureMode::Full,
+            monitor,
+            time: Local::now(),
+        }
+    }
+
+    pub fn clipboard() -> Self {
+        Self {
+            mode: CaptureMode::Clipboard,
+            monitor: None,
+            time: Local::now(),
+        }
+    }
+
+    pub fn file() -> Self {
+        Self {
+            mode: CaptureMode::File,
+            monitor: None,
+            time: Local::now(),
+        }
+    }
+}
@@ -3,6 +3,8 @@ use cairo::ImageSurface;
 use std::fs::File;
 use std::sync::OnceLock;
 
+use crate::annotate::shapes::Color;
+
 /// Shared tokio runtime — reused across captures to avoid D-Bus connection conflicts.
 fn runtime() -> &'static tokio::runtime::Runtime {
     static RT: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
@@ -44,6 +46,27 @@ pub fn capture_screenshot_path(interactive: bool) -> Result<String, String> {
     Ok(path)
 }
 
+/// Let the user click a pixel anywhere on screen via the portal's color picker.
+pub fn pick_color() -> Result<Color, String> {
+    log::debug!("Requesting color pick from portal");
+
+    let color = runtime().block_on(async {
+        ashpd::desktop::Color::pick()
+            .send()
+            .await
+            .map_err(|e| format!("Portal request failed: {}", e))?
+            .response()
+            .map_err(|e| format!("Portal response failed: {}", e))
+    })?;
+
+    Ok(Color {
+        r: color.red(),
+        g: color.green(),
+        b: color.blue(),
+        a: 1.0,
+    })
+}
+
 /// Load a PNG file into a cairo::ImageSurface
 pub fn load_image_surface(path: &str) -> Result<ImageSurface, Box<dyn std::error::Error>> {
     let mut file = File::open(path)
@@ -1,10 +1,26 @@
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+use crate::annotate::color::Color;
+use crate::config::ClipboardMode;
 
 #[derive(Parser, Debug)]
 #[command(name = "razorshot", about = "Wayland screenshot & annotation tool")]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Option<Command>,
+    /// Annotation color for this run, e.g. "#f00", "#ff000080", "rgb(255 0 0)" or "red"
+    #[arg(long, global = true, value_parser = Color::parse)]
+    pub color: Option<Color>,
+    /// Apply the `[profiles.<name>]` overrides from the config
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+    /// Don't embed any metadata (capture time, mode, software...) in saved files
+    #[arg(long, global = true)]
+    pub strip_metadata: bool,
+    /// Use this config file instead of ~/.config/razorshot/config.toml
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -14,6 +30,12 @@ pub enum Command {
         /// Skip annotation editor, save immediately
         #[arg(long)]
         no_edit: bool,
+        /// What to copy: image, path, uri, markdown, html or data-uri
+        #[arg(long, value_name = "MODE")]
+        clipboard: Option<ClipboardMode>,
+        /// Upload saved screenshots and copy their link
+        #[arg(long)]
+        upload: bool,
     },
     /// Capture the full screen
     Full {
@@ -23,16 +45,81 @@ pub enum Command {
         /// Capture a specific monitor (0-indexed)
         #[arg(long)]
         monitor: Option<u32>,
+        /// What to copy: image, path, uri, markdown, html or data-uri
+        #[arg(long, value_name = "MODE")]
+        clipboard: Option<ClipboardMode>,
+        /// Upload saved screenshots and copy their link
+        #[arg(long)]
+        upload: bool,
     },
     /// Start in system tray mode
-    Tray,
+    Tray {
+        /// What to copy: image, path, uri, markdown, html or data-uri
+        #[arg(long, value_name = "MODE")]
+        clipboard: Option<ClipboardMode>,
+        /// Upload saved screenshots and copy their link
+        #[arg(long)]
+        upload: bool,
+    },
+    /// Open the annotation editor on an existing image
+    Edit {
+        /// Edit the image on the clipboard
+        #[arg(long, required = true)]
+        clipboard: bool,
+    },
+    /// Upload a file to the `[upload]` endpoint, then print and copy its link
+    Upload { file: PathBuf },
+    /// List saved screenshots, newest first
+    History {
+        /// Only captures whose date starts with this, e.g. 2025-01 or 2025-01-31
+        #[arg(long, value_name = "DATE")]
+        date: Option<String>,
+        /// Show at most this many
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+        /// Print the entries as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Browse saved screenshots in a window
+    Gallery,
+    /// Pick a color from the screen and print its hex value
+    PickColor,
+    /// Serve clipboard contents read from stdin until another client takes over.
+    /// Started by razorshot itself so the clipboard outlives the capture.
+    #[command(name = "__serve-clipboard", hide = true)]
+    ServeClipboard {
+        #[arg(long)]
+        path: Option<PathBuf>,
+        #[arg(long)]
+        uri: Option<String>,
+        #[arg(long, default_value = "image/png")]
+        mime: String,
+    },
     /// View or modify configuration
     Config {
         /// Print current configuration
         #[arg(long)]
         show: bool,
+        /// Validate the config file and report any errors
+        #[arg(long)]
+        check: bool,
         /// Set the save directory
         #[arg(long)]
         save_dir: Option<String>,
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
     },
 }
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigAction {
+    /// Print the value of a key, e.g. `annotation.line_width`
+    Get { key: String },
+    /// Set a key, e.g. `behavior.default_action region`
+    Set { key: String, value: String },
+    /// Remove a key from config.toml so its default applies
+    Unset { key: String },
+    /// Restore one key, or the whole file (backed up first), to the defaults
+    Reset { key: Option<String> },
+}
@@ -0,0 +1,261 @@
+//! `razorshot config get/set/unset/reset`: edit single keys of config.toml in place,
+//! keeping the user's comments, formatting and key order.
+
+use std::fs;
+use std::path::Path;
+
+use toml_edit::{DocumentMut, Item, TableLike};
+
+use super::{Config, ConfigError};
+
+/// Print-ready value of a dotted key in the effective config
+pub fn get(config: &Config, key: &str) -> Result<String, ConfigError> {
+    let path = Config::config_path();
+    let root = toml::Value::try_from(config)
+        .map_err(|e| ConfigError::new(&path, format!("failed to serialize: {e}")))?;
+    let value = lookup(&root, key).ok_or_else(|| unknown_key(&path, key))?;
+    Ok(match value {
+        toml::Value::String(s) => s.clone(),
+        toml::Value::Table(t) => toml::to_string_pretty(t).unwrap_or_default(),
+        other => other.to_string(),
+    })
+}
+
+/// Set a dotted key, converting `raw` to the key's type
+pub fn set(path: &Path, key: &str, raw: &str) -> Result<(), ConfigError> {
+    let expected = schema(key).ok_or_else(|| unknown_key(path, key))?;
+    let value = typed_value(&expected, raw)
+        .map_err(|msg| ConfigError::new(path, format!("{key}: {msg}")))?;
+    let mut doc = read_document(path)?;
+    let (table, last) = parent_table(&mut doc, key, true).ok_or_else(|| unknown_key(path, key))?;
+    replace_value(table, last, value);
+    write_validated(path, key, &doc)
+}
+
+/// Remove a dotted key from the file so its default applies
+pub fn unset(path: &Path, key: &str) -> Result<(), ConfigError> {
+    schema(key).ok_or_else(|| unknown_key(path, key))?;
+    if !path.exists() {
+        return Ok(());
+    }
+    let mut doc = read_document(path)?;
+    if let Some((table, last)) = parent_table(&mut doc, key, false) {
+        table.remove(last);
+    }
+    write_validated(path, key, &doc)
+}
+
+/// Restore one key to its default value, or the whole file if `key` is None.
+/// A full reset keeps the previous file as `config.toml.bak`.
+pub fn reset(path: &Path, key: Option<&str>) -> Result<(), ConfigError> {
+    let Some(key) = key else {
+        if path.exists() {
+            let backup = path.with_extension("toml.bak");
+            fs::copy(path, &backup)
+                .map_err(|e| ConfigError::new(path, format!("failed to back up: {e}")))?;
+            println!("Previous config saved to {}", backup.display());
+        }
+        return write_file(path, &default_document());
+    };
+
+    let default = schema(key).ok_or_else(|| unknown_key(path, key))?;
+    if default.is_table() {
+        return Err(ConfigError::new(
+            path,
+            format!("`{key}` is a section; reset its keys individually or reset everything"),
+        ));
+    }
+    let value: toml_edit::Value = default
+        .to_string()
+        .parse()
+        .map_err(|e| ConfigError::new(path, format!("{key}: {e}")))?;
+    let mut doc = read_document(path)?;
+    let (table, last) = parent_table(&mut doc, key, true).ok_or_else(|| unknown_key(path, key))?;
+    replace_value(table, last, value);
+    write_validated(path, key, &doc)
+}
+
+/// Convert `raw` to the type of `key`, for values that come from outside the
+/// file such as environment variables
+pub(super) fn parse_value(key: &str, raw: &str) -> Result<toml::Value, String> {
+    let expected = schema(key).ok_or_else(|| format!("unknown key `{key}`"))?;
+    let value = typed_value(&expected, raw)?;
+    let mut table: toml::Table = format!("value = {value}")
+        .parse()
+        .map_err(|e: toml::de::Error| e.message().to_string())?;
+    table.remove("value").ok_or_else(|| format!("invalid value \"{raw}\""))
+}
+
+/// Whether `key` names a setting, e.g. before reading it from the environment
+pub(super) fn is_key(key: &str) -> bool {
+    schema(key).is_some()
+}
+
+/// Tables of free-form string keys, such as header names, empty by default
+const STRING_MAPS: [&str; 2] = ["upload.http.headers", "upload.http.form"];
+
+/// The default value for a dotted key; None if the key doesn't exist.
+/// `profiles.<name>.<key>` takes the type of the `<key>` it overrides, and
+/// any key directly inside one of `STRING_MAPS` is a string.
+fn schema(key: &str) -> Option<toml::Value> {
+    let key = match key.strip_prefix("profiles.") {
+        Some(rest) => rest.split_once('.')?.1,
+        None => key,
+    };
+    if let Some((table, name)) = key.rsplit_once('.') {
+        if STRING_MAPS.contains(&table) && !name.is_empty() {
+            return Some(toml::Value::String(String::new()));
+        }
+    }
+    let root = toml::Value::try_from(Config::default()).ok()?;
+    lookup(&root, key).cloned()
+}
+
+fn lookup<'a>(root: &'a toml::Value, key: &str) -> Option<&'a toml::Value> {
+    key.split('.').try_fold(root, |value, part| value.get(part))
+}
+
+/// All settable dotted keys, for error messages
+fn known_keys() -> Vec<String> {
+    fn walk(prefix: &str, value: &toml::Value, out: &mut Vec<String>) {
+        match value {
+            toml::Value::Table(table) => {
+                for (k, v) in table {
+                    let key = if prefix.is_empty() {
+                        k.clone()
+                    } else {
+                        format!("{prefix}.{k}")
+                    };
+                    walk(&key, v, out);
+                }
+            }
+            _ => out.push(prefix.to_string()),
+        }
+    }
+    let mut keys = Vec::new();
+    if let Ok(root) = toml::Value::try_from(Config::default()) {
+        walk("", &root, &mut keys);
+    }
+    keys
+}
+
+fn unknown_key(path: &Path, key: &str) -> ConfigError {
+    ConfigError::new(
+        path,
+        format!(
+            "unknown key `{key}`; valid keys are: {}, {} (or profiles.<name>.<key>)",
+            known_keys().join(", "),
+            STRING_MAPS.map(|table| format!("{table}.<name>")).join(", ")
+        ),
+    )
+}
+
+/// Convert a command-line string to a TOML value of the same type as `expected`
+fn typed_value(expected: &toml::Value, raw: &str) -> Result<toml_edit::Value, String> {
+    let trimmed = raw.trim();
+    match expected {
+        toml::Value::String(_) => Ok(raw.into()),
+        toml::Value::Integer(_) => trimmed
+            .parse::<i64>()
+            .map(Into::into)
+            .map_err(|_| format!("expected an integer, got \"{raw}\"")),
+        toml::Value::Float(_) => trimmed
+            .parse::<f64>()
+            .map(Into::into)
+            .map_err(|_| format!("expected a number, got \"{raw}\"")),
+        toml::Value::Boolean(_) => trimmed
+            .parse::<bool>()
+            .map(Into::into)
+            .map_err(|_| format!("expected true or false, got \"{raw}\"")),
+        toml::Value::Array(_) => {
+            if trimmed.starts_with('[') {
+                match trimmed.parse::<toml_edit::Value>() {
+                    Ok(v @ toml_edit::Value::Array(_)) => Ok(v),
+                    _ => Err(format!("expected a TOML array, got \"{raw}\"")),
+                }
+            } else {
+                // Comma-separated shorthand: a, b, c
+                let items: toml_edit::Array = trimmed
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .collect();
+                Ok(toml_edit::Value::Array(items))
+            }
+        }
+        toml::Value::Table(_) => Err("is a section; set one of its keys instead".into()),
+        toml::Value::Datetime(_) => Err("datetime values are not supported".into()),
+    }
+}
+
+/// Walk to the table holding the last segment of `key`, creating
+/// intermediate tables when `create` is set.
+fn parent_table<'a, 'k>(
+    doc: &'a mut DocumentMut,
+    key: &'k str,
+    create: bool,
+) -> Option<(&'a mut dyn TableLike, &'k str)> {
+    let mut parts: Vec<&str> = key.split('.').collect();
+    let last = parts.pop()?;
+    let mut table: &mut dyn TableLike = doc.as_table_mut();
+    for part in parts {
+        if create && table.get(part).is_none() {
+            table.insert(part, Item::Table(toml_edit::Table::new()));
+        }
+        table = table.get_mut(part)?.as_table_like_mut()?;
+    }
+    Some((table, last))
+}
+
+/// Replace (or add) a value, keeping the old value's surrounding comments
+fn replace_value(table: &mut dyn TableLike, key: &str, mut value: toml_edit::Value) {
+    match table.get_mut(key) {
+        Some(item) => {
+            if let Some(old) = item.as_value() {
+                *value.decor_mut() = old.decor().clone();
+            }
+            *item = Item::Value(value);
+        }
+        None => {
+            table.insert(key, Item::Value(value));
+        }
+    }
+}
+
+fn default_document() -> DocumentMut {
+    toml::to_string_pretty(&Config::default())
+        .unwrap_or_default()
+        .parse()
+        .unwrap_or_default()
+}
+
+/// Parse the config file for editing; a missing file starts from the defaults
+fn read_document(path: &Path) -> Result<DocumentMut, ConfigError> {
+    if !path.exists() {
+        return Ok(default_document());
+    }
+    let contents = fs::read_to_string(path)
+        .map_err(|e| ConfigError::new(path, format!("failed to read: {e}")))?;
+    contents.parse::<DocumentMut>().map_err(|e| ConfigError {
+        path: path.to_path_buf(),
+        position: e.span().map(|span| super::line_column(&contents, span.start)),
+        message: e.message().to_string(),
+    })
+}
+
+/// Write the edited document, but only if the result is a valid config
+fn write_validated(path: &Path, key: &str, doc: &DocumentMut) -> Result<(), ConfigError> {
+    if let Err(mut e) = Config::parse(path, &doc.to_string()) {
+        e.message = format!("not saved, changing `{key}` leaves an invalid config: {}", e.message);
+        return Err(e);
+    }
+    write_file(path, doc)
+}
+
+fn write_file(path: &Path, doc: &DocumentMut) -> Result<(), ConfigError> {
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    fs::write(path, doc.to_string())
+        .map_err(|e| ConfigError::new(path, format!("failed to write: {e}")))
+}
@@ -0,0 +1,123 @@
+//! Upgrade config files written by older versions. Each step edits the TOML
+//! document in place, so comments and formatting survive the upgrade.
+
+use std::fs;
+use std::path::Path;
+
+use toml_edit::{DocumentMut, Item, TableLike};
+
+use super::{Config, ConfigError, CONFIG_VERSION};
+
+/// Upgrade steps; `STEPS[n]` turns a version `n` file into version `n + 1`
+const STEPS: [fn(&mut DocumentMut); CONFIG_VERSION as usize] = [v0_to_v1, v1_to_v2];
+
+/// Bring the file at `path` up to `CONFIG_VERSION`, keeping the original as
+/// `config.toml.v<old>.bak`. Returns the version the file was upgraded from,
+/// or None if it was already current.
+///
+/// A file that doesn't parse or wouldn't validate after the upgrade is left
+/// untouched; loading it reports the problem.
+pub(super) fn upgrade(path: &Path) -> Result<Option<u32>, ConfigError> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| ConfigError::new(path, format!("failed to read: {e}")))?;
+    let Ok(mut doc) = contents.parse::<DocumentMut>() else {
+        return Ok(None);
+    };
+
+    let version = match doc.get("version").and_then(Item::as_integer) {
+        Some(v) => u32::try_from(v).unwrap_or(0),
+        None => 0,
+    };
+    if version >= CONFIG_VERSION {
+        if version > CONFIG_VERSION {
+            log::warn!(
+                "{} is version {version}, newer than this razorshot ({CONFIG_VERSION}); unknown keys are ignored",
+                path.display()
+            );
+        }
+        return Ok(None);
+    }
+
+    for step in &STEPS[version as usize..] {
+        step(&mut doc);
+    }
+    doc.insert("version", toml_edit::value(i64::from(CONFIG_VERSION)));
+
+    let upgraded = doc.to_string();
+    if Config::parse(path, &upgraded).is_err() {
+        return Ok(None);
+    }
+
+    let backup = path.with_extension(format!("toml.v{version}.bak"));
+    fs::copy(path, &backup)
+        .map_err(|e| ConfigError::new(path, format!("failed to back up: {e}")))?;
+    fs::write(path, upgraded)
+        .map_err(|e| ConfigError::new(path, format!("failed to write: {e}")))?;
+    log::info!(
+        "Upgraded {} from version {version} to {CONFIG_VERSION}; previous file saved to {}",
+        path.display(),
+        backup.display()
+    );
+    Ok(Some(version))
+}
+
+/// Before versioning, `filename_template` could end in an image extension.
+/// The extension now comes from `export_format`, so drop it, and keep JPEG
+/// output for templates that asked for it.
+fn v0_to_v1(doc: &mut DocumentMut) {
+    let Some(template) = doc.get("filename_template").and_then(Item::as_str) else {
+        return;
+    };
+    let lower = template.to_ascii_lowercase();
+    let Some((stem, ext)) = [".png", ".jpg", ".jpeg"]
+        .iter()
+        .find(|ext| lower.ends_with(*ext))
+        .map(|ext| (template[..template.len() - ext.len()].to_string(), *ext))
+    else {
+        return;
+    };
+
+    if let Some(value) = doc["filename_template"].as_value_mut() {
+        let decor = value.decor().clone();
+        *value = stem.into();
+        *value.decor_mut() = decor;
+    }
+    if ext != ".png" && doc.get("export_format").is_none() {
+        doc.insert("export_format", toml_edit::value("jpg"));
+    }
+}
+
+/// `annotation.jpeg_quality` moved to the new `[export]` section, in the
+/// base config and in every profile.
+fn v1_to_v2(doc: &mut DocumentMut) {
+    move_jpeg_quality(doc.as_table_mut());
+    if let Some(profiles) = doc.get_mut("profiles").and_then(Item::as_table_like_mut) {
+        for (_, profile) in profiles.iter_mut() {
+            if let Some(profile) = profile.as_table_like_mut() {
+                move_jpeg_quality(profile);
+            }
+        }
+    }
+}
+
+fn move_jpeg_quality(table: &mut dyn TableLike) {
+    let Some(annotation) = table.get_mut("annotation").and_then(Item::as_table_like_mut) else {
+        return;
+    };
+    // Clone the key too, so comments attached to it move along
+    let (Some(key), Some(quality)) = (
+        annotation.key("jpeg_quality").cloned(),
+        annotation.remove("jpeg_quality"),
+    ) else {
+        return;
+    };
+    if annotation.is_empty() {
+        table.remove("annotation");
+    }
+    let export = table
+        .entry("export")
+        .or_insert(Item::Table(toml_edit::Table::new()));
+    if let Some(export) = export.as_table_like_mut() {
+        export.entry_format(&key).or_insert(quality);
+    }
+}
@@ -0,0 +1,965 @@
+use serde::de::{Error as _, IntoDeserializer};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::annotate::color::Color;
+
+pub mod edit;
+mod migrate;
+
+/// Schema version written by this build; see `migrate` for the upgrade steps
+pub const CONFIG_VERSION: u32 = 2;
+
+/// Prefix of environment variables that override config keys
+const ENV_PREFIX: &str = "RAZORSHOT_";
+
+/// Config file given with `--config`, if any
+static CONFIG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Every section defaults field by field, so a file written by an older
+/// version that lacks newer keys still loads.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Config {
+    /// Schema version of the file. Files from before versioning have none and read as 0.
+    #[serde(default)]
+    pub version: u32,
+    pub save_dir: String,
+    /// strftime format plus `{token}`s; `/` separates subdirectories
+    #[serde(deserialize_with = "de_filename_template")]
+    pub filename_template: String,
+    #[serde(deserialize_with = "de_lowercase")]
+    pub export_format: ExportFormat,
+    pub annotation: AnnotationConfig,
+    pub behavior: BehaviorConfig,
+    pub export: ExportConfig,
+    pub metadata: MetadataConfig,
+    pub hooks: HooksConfig,
+    pub upload: UploadConfig,
+    pub history: HistoryConfig,
+    /// Named overrides, selected with `--profile <name>` or from the tray menu
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, toml::Table>,
+    /// The profile applied by `layered`, for the `{profile}` filename token
+    #[serde(skip)]
+    pub active_profile: Option<String>,
+}
+
+/// Layers applied on top of the config file, in order: a profile, then overrides
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Profile from `[profiles.<name>]` to apply
+    pub profile: Option<String>,
+    /// Values from `RAZORSHOT_*` variables and the command line, applied last
+    pub overrides: toml::Table,
+}
+
+impl LoadOptions {
+    /// Options from the environment: `RAZORSHOT_PROFILE` selects a profile and
+    /// `RAZORSHOT_<KEY>` overrides a key, with `__` between nested parts,
+    /// e.g. `RAZORSHOT_ANNOTATION__LINE_WIDTH=5`. Variables that name no key
+    /// are skipped with a warning; a bad value for a known key is an error.
+    pub fn from_env() -> Result<Self, String> {
+        let mut options = Self::default();
+        for (name, raw) in env::vars() {
+            let Some(rest) = name.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            match rest {
+                "CONFIG" => {}
+                // Set for hook commands, describing the screenshot
+                _ if rest.starts_with("HOOK_") => {}
+                "PROFILE" => options.profile = Some(raw).filter(|p| !p.is_empty()),
+                _ => {
+                    let key = rest.to_lowercase().replace("__", ".");
+                    // A stray variable, e.g. from a wrapper script, shouldn't stop razorshot
+                    if !edit::is_key(&key) {
+                        log::warn!("Ignoring {name}: `{key}` is not a config key");
+                        continue;
+                    }
+                    let value = edit::parse_value(&key, &raw).map_err(|e| format!("{name}: {e}"))?;
+                    options.set(&key, value);
+                }
+            }
+        }
+        Ok(options)
+    }
+
+    /// Add an override for a dotted key, e.g. `annotation.default_color`
+    pub fn set(&mut self, key: &str, value: impl Into<toml::Value>) {
+        let mut parts: Vec<&str> = key.split('.').collect();
+        let Some(last) = parts.pop() else { return };
+        let mut table = &mut self.overrides;
+        for part in parts {
+            let entry = table
+                .entry(part.to_string())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            if !entry.is_table() {
+                *entry = toml::Value::Table(toml::Table::new());
+            }
+            let toml::Value::Table(inner) = entry else { return };
+            table = inner;
+        }
+        table.insert(last.to_string(), value.into());
+    }
+}
+
+/// Image format used when saving screenshots. Unknown names are a config
+/// error rather than a silent fallback to PNG.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    #[default]
+    #[serde(rename = "png")]
+    Png,
+    #[serde(rename = "jpg", alias = "jpeg")]
+    Jpeg,
+    #[serde(rename = "webp")]
+    Webp,
+    #[serde(rename = "qoi")]
+    Qoi,
+    #[serde(rename = "bmp")]
+    Bmp,
+    #[serde(rename = "tiff", alias = "tif")]
+    Tiff,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 6] = [
+        ExportFormat::Png,
+        ExportFormat::Jpeg,
+        ExportFormat::Webp,
+        ExportFormat::Qoi,
+        ExportFormat::Bmp,
+        ExportFormat::Tiff,
+    ];
+
+    /// The format for a file extension, accepting the same aliases as the config
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "png" => Some(ExportFormat::Png),
+            "jpg" | "jpeg" => Some(ExportFormat::Jpeg),
+            "webp" => Some(ExportFormat::Webp),
+            "qoi" => Some(ExportFormat::Qoi),
+            "bmp" => Some(ExportFormat::Bmp),
+            "tiff" | "tif" => Some(ExportFormat::Tiff),
+            _ => None,
+        }
+    }
+
+    /// MIME type of files in this format
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ExportFormat::Png => "image/png",
+            ExportFormat::Jpeg => "image/jpeg",
+            ExportFormat::Webp => "image/webp",
+            ExportFormat::Qoi => "image/qoi",
+            ExportFormat::Bmp => "image/bmp",
+            ExportFormat::Tiff => "image/tiff",
+        }
+    }
+
+    /// File extension, without the dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Png => "png",
+            ExportFormat::Jpeg => "jpg",
+            ExportFormat::Webp => "webp",
+            ExportFormat::Qoi => "qoi",
+            ExportFormat::Bmp => "bmp",
+            ExportFormat::Tiff => "tiff",
+        }
+    }
+}
+
+/// Encoder settings for the export formats
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ExportConfig {
+    #[serde(deserialize_with = "de_jpeg_quality")]
+    pub jpeg_quality: u8,
+    /// Quality for lossy WebP, 0-100
+    #[serde(deserialize_with = "de_webp_quality")]
+    pub webp_quality: u8,
+    /// Write WebP losslessly; `webp_quality` is then ignored
+    pub webp_lossless: bool,
+    /// zlib level for PNG, 0 (store) to 9 (smallest)
+    #[serde(deserialize_with = "de_png_compression")]
+    pub png_compression: u8,
+    pub png_filter: PngFilter,
+    /// Write an indexed PNG when the image has at most 256 colors (lossless)
+    pub png_palette: bool,
+    /// Drop the alpha channel when every pixel is opaque
+    pub png_strip_alpha: bool,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            jpeg_quality: 90,
+            webp_quality: 80,
+            webp_lossless: false,
+            png_compression: 6,
+            png_filter: PngFilter::Adaptive,
+            png_palette: true,
+            png_strip_alpha: true,
+        }
+    }
+}
+
+/// Which provenance fields are embedded in saved PNG and JPEG files
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct MetadataConfig {
+    /// Embed nothing at all, overriding the keys below (also `--strip-metadata`)
+    pub strip: bool,
+    pub capture_time: bool,
+    /// `region` or `full`
+    pub capture_mode: bool,
+    pub monitor: bool,
+    /// Razorshot name and version
+    pub software: bool,
+    /// The annotation shapes as JSON
+    pub annotations: bool,
+}
+
+impl Default for MetadataConfig {
+    fn default() -> Self {
+        Self {
+            strip: false,
+            capture_time: true,
+            capture_mode: true,
+            monitor: true,
+            software: true,
+            annotations: false,
+        }
+    }
+}
+
+/// Shell commands run in the background after a capture or a save. Empty
+/// means none. See `output::hooks` for what they receive.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Run when a screenshot is taken, before it is edited or saved
+    pub post_capture: String,
+    /// Run after each file is saved
+    pub post_save: String,
+    /// Seconds a hook may run before it is killed
+    #[serde(deserialize_with = "de_hook_timeout")]
+    pub timeout: u64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            post_capture: String::new(),
+            post_save: String::new(),
+            timeout: 30,
+        }
+    }
+}
+
+/// The index of saved screenshots behind `razorshot history` and the gallery
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    /// Entries kept; the oldest are dropped first (their files stay)
+    #[serde(deserialize_with = "de_history_limit")]
+    pub limit: usize,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            limit: 1000,
+        }
+    }
+}
+
+/// Sharing screenshots by uploading them
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct UploadConfig {
+    /// Upload every saved screenshot (also `--upload` and the tray toggle)
+    pub auto: bool,
+    pub target: UploadTarget,
+    pub http: HttpUploadConfig,
+    pub s3: S3UploadConfig,
+}
+
+impl UploadConfig {
+    /// Whether the selected target has somewhere to upload to
+    pub fn is_configured(&self) -> bool {
+        match self.target {
+            UploadTarget::Http => !self.http.url.is_empty(),
+            UploadTarget::S3 => !self.s3.bucket.is_empty(),
+        }
+    }
+}
+
+/// Where uploads go
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadTarget {
+    /// `[upload.http]`
+    #[default]
+    Http,
+    /// `[upload.s3]`
+    S3,
+}
+
+/// A generic HTTP endpoint that takes a file and answers with its link
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HttpUploadConfig {
+    /// Endpoint; `{filename}` is replaced with the file's name
+    pub url: String,
+    pub method: HttpMethod,
+    /// Form field holding the file, for `post`
+    pub field: String,
+    /// Extra form fields sent along with the file, for `post`
+    pub form: BTreeMap<String, String>,
+    /// Request headers, e.g. `Authorization`
+    pub headers: BTreeMap<String, String>,
+    /// Dotted path to the link in a JSON response, e.g. `data.link`;
+    /// empty means the whole response body is the link
+    pub response_url: String,
+    /// Seconds before the upload is abandoned
+    #[serde(deserialize_with = "de_upload_timeout")]
+    pub timeout: u64,
+}
+
+impl Default for HttpUploadConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            method: HttpMethod::Post,
+            field: "file".into(),
+            form: BTreeMap::new(),
+            headers: BTreeMap::new(),
+            response_url: String::new(),
+            timeout: 60,
+        }
+    }
+}
+
+/// An S3-compatible bucket (AWS, MinIO, R2, ...), signed with SigV4
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct S3UploadConfig {
+    /// Service URL, e.g. `https://s3.eu-central-1.amazonaws.com` or `http://localhost:9000`
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    /// Key prefix, in `filename_template` syntax, e.g. `screenshots/%Y/%m`
+    #[serde(deserialize_with = "de_filename_template")]
+    pub prefix: String,
+    /// `endpoint/bucket/key` instead of `bucket.endpoint/key`; MinIO needs this
+    pub path_style: bool,
+    /// Empty means `$AWS_ACCESS_KEY_ID`
+    pub access_key: String,
+    /// Empty means `$AWS_SECRET_ACCESS_KEY`
+    pub secret_key: String,
+    /// Link with a presigned URL, for private buckets
+    pub presign: bool,
+    /// Seconds a presigned link stays valid (at most 7 days)
+    #[serde(deserialize_with = "de_presign_expiry")]
+    pub presign_expiry: u64,
+    /// Base URL for links instead of the object's URL, e.g. a CDN
+    pub public_url: String,
+    /// Seconds before the upload is abandoned
+    #[serde(deserialize_with = "de_upload_timeout")]
+    pub timeout: u64,
+}
+
+impl Default for S3UploadConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: "https://s3.amazonaws.com".into(),
+            region: "us-east-1".into(),
+            bucket: String::new(),
+            prefix: String::new(),
+            path_style: false,
+            access_key: String::new(),
+            secret_key: String::new(),
+            presign: false,
+            presign_expiry: 604_800,
+            public_url: String::new(),
+            timeout: 60,
+        }
+    }
+}
+
+/// How the file is sent
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpMethod {
+    /// `multipart/form-data` with the file in `field`
+    #[default]
+    Post,
+    /// The file as the raw request body
+    Put,
+}
+
+/// PNG row filter; `adaptive` picks the best one per row
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PngFilter {
+    None,
+    Sub,
+    Up,
+    Avg,
+    Paeth,
+    #[default]
+    Adaptive,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AnnotationConfig {
+    #[serde(deserialize_with = "de_color")]
+    pub default_color: String,
+    #[serde(deserialize_with = "de_line_width")]
+    pub line_width: f64,
+    #[serde(deserialize_with = "de_font_size")]
+    pub font_size: f64,
+    #[serde(deserialize_with = "de_blur_block_size")]
+    pub blur_block_size: u32,
+    /// Preset colors shown as swatches in the toolbar (keys 1-9 select them)
+    #[serde(deserialize_with = "de_palette")]
+    pub palette: Vec<String>,
+}
+
+fn default_palette() -> Vec<String> {
+    [
+        "#ff0000", "#ff8800", "#ffdd00", "#22bb33", "#0088ff", "#8844dd", "#000000", "#ffffff",
+    ]
+    .iter()
+    .map(|c| c.to_string())
+    .collect()
+}
+
+impl Default for AnnotationConfig {
+    fn default() -> Self {
+        Self {
+            default_color: "#ff0000".into(),
+            line_width: 3.0,
+            font_size: 16.0,
+            blur_block_size: 10,
+            palette: default_palette(),
+        }
+    }
+}
+
+impl AnnotationConfig {
+    /// The configured default color, or red if it doesn't parse
+    pub fn color(&self) -> Color {
+        Color::parse(&self.default_color).unwrap_or(Color {
+            r: 1.0,
+            g: 0.0,
+            b: 0.0,
+            a: 1.0,
+        })
+    }
+
+    /// The palette colors that parse; invalid entries are skipped
+    pub fn palette_colors(&self) -> Vec<Color> {
+        self.palette.iter().filter_map(|c| Color::parse(c).ok()).collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct BehaviorConfig {
+    pub open_editor: bool,
+    pub copy_to_clipboard: bool,
+    /// What is put on the clipboard when `copy_to_clipboard` is on
+    pub clipboard_mode: ClipboardMode,
+    pub show_notification: bool,
+    #[serde(deserialize_with = "de_lowercase")]
+    pub default_action: DefaultAction,
+    /// What the editor's Done button does before closing
+    pub done_action: DoneAction,
+}
+
+impl Default for BehaviorConfig {
+    fn default() -> Self {
+        Self {
+            open_editor: true,
+            copy_to_clipboard: true,
+            clipboard_mode: ClipboardMode::Image,
+            show_notification: true,
+            default_action: DefaultAction::Tray,
+            done_action: DoneAction::Save,
+        }
+    }
+}
+
+/// What `razorshot` does when run without a subcommand
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DefaultAction {
+    #[default]
+    Tray,
+    Region,
+    Full,
+}
+
+/// What the editor's Done button does before closing the editor
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum DoneAction {
+    /// Save to `save_dir`, and copy if `copy_to_clipboard` is on
+    #[default]
+    Save,
+    /// Copy without saving a file
+    Copy,
+    /// Ask where to save; the editor stays open if the dialog is cancelled
+    SaveAs,
+}
+
+/// Clipboard payload. Everything except `image` and `data-uri` refers to the
+/// saved file, and falls back to the image when saving failed.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClipboardMode {
+    /// The image, plus the saved file as `text/uri-list` and its path
+    #[default]
+    Image,
+    /// The saved file's path
+    Path,
+    /// The saved file as a `file://` URI
+    Uri,
+    /// `![screenshot](path)`
+    Markdown,
+    /// `<img src="file://...">`, as `text/html`
+    Html,
+    /// The image as a `data:image/png;base64,...` URI
+    DataUri,
+}
+
+impl ClipboardMode {
+    /// Name as written in the config
+    pub fn name(self) -> &'static str {
+        match self {
+            ClipboardMode::Image => "image",
+            ClipboardMode::Path => "path",
+            ClipboardMode::Uri => "uri",
+            ClipboardMode::Markdown => "markdown",
+            ClipboardMode::Html => "html",
+            ClipboardMode::DataUri => "data-uri",
+        }
+    }
+}
+
+// Field validators. Running them during deserialization lets the toml error
+// point at the offending value.
+
+fn in_range<'de, D, T>(deserializer: D, range: RangeInclusive<T>) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + PartialOrd + fmt::Display,
+{
+    let value = T::deserialize(deserializer)?;
+    if range.contains(&value) {
+        Ok(value)
+    } else {
+        Err(D::Error::custom(format!(
+            "must be between {} and {}, got {}",
+            range.start(),
+            range.end(),
+            value
+        )))
+    }
+}
+
+fn de_line_width<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+    in_range(d, 1.0..=20.0)
+}
+
+fn de_font_size<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+    in_range(d, 8.0..=72.0)
+}
+
+fn de_blur_block_size<'de, D: Deserializer<'de>>(d: D) -> Result<u32, D::Error> {
+    in_range(d, 2..=100)
+}
+
+fn de_jpeg_quality<'de, D: Deserializer<'de>>(d: D) -> Result<u8, D::Error> {
+    in_range(d, 1..=100)
+}
+
+fn de_png_compression<'de, D: Deserializer<'de>>(d: D) -> Result<u8, D::Error> {
+    in_range(d, 0..=9)
+}
+
+fn de_webp_quality<'de, D: Deserializer<'de>>(d: D) -> Result<u8, D::Error> {
+    in_range(d, 0..=100)
+}
+
+fn de_hook_timeout<'de, D: Deserializer<'de>>(d: D) -> Result<u64, D::Error> {
+    in_range(d, 1..=3600)
+}
+
+fn de_upload_timeout<'de, D: Deserializer<'de>>(d: D) -> Result<u64, D::Error> {
+    in_range(d, 1..=3600)
+}
+
+fn de_history_limit<'de, D: Deserializer<'de>>(d: D) -> Result<usize, D::Error> {
+    in_range(d, 1..=100_000)
+}
+
+fn de_presign_expiry<'de, D: Deserializer<'de>>(d: D) -> Result<u64, D::Error> {
+    // SigV4 caps presigned URLs at a week
+    in_range(d, 1..=604_800)
+}
+
+/// A name-valued enum, ignoring case: older versions accepted
+/// `export_format = "PNG"`
+fn de_lowercase<'de, D, T>(d: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    let value = String::deserialize(d)?;
+    T::deserialize(value.to_ascii_lowercase().into_deserializer())
+}
+
+fn de_color<'de, D: Deserializer<'de>>(d: D) -> Result<String, D::Error> {
+    let value = String::deserialize(d)?;
+    Color::parse(&value).map_err(D::Error::custom)?;
+    Ok(value)
+}
+
+fn de_filename_template<'de, D: Deserializer<'de>>(d: D) -> Result<String, D::Error> {
+    let value = String::deserialize(d)?;
+    crate::output::filename::check_template(&value).map_err(D::Error::custom)?;
+    Ok(value)
+}
+
+fn de_palette<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<String>, D::Error> {
+    let values = Vec::<String>::deserialize(d)?;
+    for value in &values {
+        Color::parse(value).map_err(D::Error::custom)?;
+    }
+    Ok(values)
+}
+
+/// A problem with the config file, with its location when known
+#[derive(Debug)]
+pub struct ConfigError {
+    pub path: PathBuf,
+    /// 1-based line and column of the offending value
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl ConfigError {
+    fn new(path: &Path, message: impl Into<String>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            position: None,
+            message: message.into(),
+        }
+    }
+
+    fn from_toml(path: &Path, contents: &str, err: &toml::de::Error) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            position: err.span().map(|span| line_column(contents, span.start)),
+            message: err.message().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(
+                f,
+                "{}:{}:{}: {}",
+                self.path.display(),
+                line,
+                column,
+                self.message
+            ),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Convert a byte offset into a 1-based (line, column) pair
+fn line_column(contents: &str, offset: usize) -> (usize, usize) {
+    let before = &contents[..offset.min(contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    (line, column)
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            save_dir: "$XDG_PICTURES_DIR/Screenshots".into(),
+            filename_template: "Screenshot_%Y-%m-%d_%H-%M-%S".into(),
+            export_format: ExportFormat::Png,
+            annotation: AnnotationConfig::default(),
+            behavior: BehaviorConfig::default(),
+            export: ExportConfig::default(),
+            metadata: MetadataConfig::default(),
+            hooks: HooksConfig::default(),
+            upload: UploadConfig::default(),
+            history: HistoryConfig::default(),
+            profiles: BTreeMap::new(),
+            active_profile: None,
+        }
+    }
+}
+
+impl Config {
+    /// Use `path` instead of the default config file for the rest of the run
+    pub fn set_config_path(path: PathBuf) {
+        let _ = CONFIG_PATH.set(path);
+    }
+
+    /// The config file: `--config`, then `$RAZORSHOT_CONFIG`, then
+    /// `$XDG_CONFIG_HOME/razorshot/config.toml`
+    pub fn config_path() -> PathBuf {
+        if let Some(path) = CONFIG_PATH.get() {
+            return path.clone();
+        }
+        if let Some(path) = env::var_os("RAZORSHOT_CONFIG").filter(|p| !p.is_empty()) {
+            return PathBuf::from(path);
+        }
+        // Relative values of XDG_CONFIG_HOME are invalid per the spec and ignored
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(dirs::config_dir)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".config")))
+            .unwrap_or_else(|| PathBuf::from(".config"));
+        config_dir.join("razorshot").join("config.toml")
+    }
+
+    /// Load the user's config, creating it with defaults on first run and
+    /// upgrading it if it was written by an older version.
+    /// An invalid config is reported and replaced by defaults for this run only;
+    /// the file itself is left for the user to fix.
+    pub fn load() -> Self {
+        let path = Self::config_path();
+        if !path.exists() {
+            let config = Config::default();
+            if let Err(e) = config.save() {
+                log::error!("Failed to write default config: {}", e);
+            }
+            return config;
+        }
+        if let Err(e) = migrate::upgrade(&path) {
+            log::error!("Failed to upgrade config: {}", e);
+        }
+        match Self::read(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                log::error!("{}", e);
+                log::error!("Using default settings; fix the file or run `razorshot config --check`");
+                Config::default()
+            }
+        }
+    }
+
+    /// Re-read the config file for a running instance. Unlike `load`, an invalid
+    /// file is an error so the caller can keep its current settings.
+    pub fn reload() -> Result<Self, ConfigError> {
+        let path = Self::config_path();
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        if let Err(e) = migrate::upgrade(&path) {
+            log::error!("Failed to upgrade config: {}", e);
+        }
+        Self::read(&path)
+    }
+
+    /// Load the config file and apply the profile and overrides in `options`
+    pub fn load_with(options: &LoadOptions) -> Result<Self, ConfigError> {
+        Self::load().layered(options.profile.as_deref(), &options.overrides)
+    }
+
+    /// Like `load_with`, but an invalid file is an error instead of the
+    /// defaults, so a running tray can keep its current settings
+    pub fn reload_with(options: &LoadOptions) -> Result<Self, ConfigError> {
+        Self::reload()?.layered(options.profile.as_deref(), &options.overrides)
+    }
+
+    /// Build the effective config: this one, then the named profile, then `overrides`.
+    /// The merged result goes through the same validation as the file itself.
+    pub fn layered(&self, profile: Option<&str>, overrides: &toml::Table) -> Result<Self, ConfigError> {
+        let path = Self::config_path();
+        let mut base = self.clone();
+        let profiles = std::mem::take(&mut base.profiles);
+
+        let mut merged = match toml::Value::try_from(&base) {
+            Ok(toml::Value::Table(table)) => table,
+            Ok(_) => toml::Table::new(),
+            Err(e) => return Err(ConfigError::new(&path, format!("failed to serialize: {e}"))),
+        };
+        if let Some(name) = profile {
+            let layer = profiles.get(name).ok_or_else(|| {
+                let known: Vec<&str> = profiles.keys().map(String::as_str).collect();
+                ConfigError::new(
+                    &path,
+                    format!("unknown profile `{name}`; defined profiles: {}", known.join(", ")),
+                )
+            })?;
+            merge_tables(&mut merged, layer);
+        }
+        merge_tables(&mut merged, overrides);
+
+        let mut config: Config = toml::Value::Table(merged).try_into().map_err(|e: toml::de::Error| {
+            let layer = profile.map_or("override".to_string(), |p| format!("profiles.{p}"));
+            ConfigError::new(&path, format!("{layer}: {}", e.message()))
+        })?;
+        config.profiles = profiles;
+        config.active_profile = profile.map(str::to_string);
+        Ok(config)
+    }
+
+    /// Read and validate a config file
+    pub fn read(path: &Path) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ConfigError::new(path, format!("failed to read: {e}")))?;
+        Self::parse(path, &contents)
+    }
+
+    /// Validate config file contents; `path` is only used for error messages
+    pub fn parse(path: &Path, contents: &str) -> Result<Self, ConfigError> {
+        let config: Config =
+            toml::from_str(contents).map_err(|e| ConfigError::from_toml(path, contents, &e))?;
+        for name in config.profiles.keys() {
+            if let Err(e) = config.layered(Some(name), &toml::Table::new()) {
+                return Err(ConfigError::new(path, e.message));
+            }
+        }
+        Ok(config)
+    }
+
+    /// Write the config file. Refuses to replace an existing file that doesn't
+    /// validate, so hand edits are never lost to a fallback config.
+    pub fn save(&self) -> Result<(), ConfigError> {
+        let path = Self::config_path();
+        if path.exists() {
+            Self::read(&path)?;
+        }
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let contents = toml::to_string_pretty(self)
+            .map_err(|e| ConfigError::new(&path, format!("failed to serialize: {e}")))?;
+        fs::write(&path, contents)
+            .map_err(|e| ConfigError::new(&path, format!("failed to write: {e}")))
+    }
+
+    /// Expand `~`, `~user`, `$VAR` and `${VAR}` in save_dir.
+    /// `$XDG_PICTURES_DIR` falls back to the entry in `user-dirs.dirs`.
+    pub fn resolve_save_dir(&self) -> PathBuf {
+        PathBuf::from(expand_vars(&expand_tilde(&self.save_dir)))
+    }
+}
+
+/// Replace a leading `~` or `~user` with that user's home directory
+fn expand_tilde(path: &str) -> String {
+    let Some(rest) = path.strip_prefix('~') else {
+        return path.to_string();
+    };
+    let (user, tail) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let home = if user.is_empty() {
+        dirs::home_dir()
+    } else {
+        user_home(user)
+    };
+    match home {
+        Some(home) => format!("{}{}", home.display(), tail),
+        None => path.to_string(),
+    }
+}
+
+/// Home directory of another user, from /etc/passwd
+fn user_home(user: &str) -> Option<PathBuf> {
+    let passwd = fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split(':').collect();
+        (fields.len() > 5 && fields[0] == user).then(|| PathBuf::from(fields[5]))
+    })
+}
+
+/// Substitute `$VAR` and `${VAR}`. Unknown variables are left as written.
+fn expand_vars(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    let mut rest = path;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let (name, len) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            },
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+        match lookup_var(name) {
+            Some(value) if !name.is_empty() => out.push_str(&value),
+            _ => {
+                if !name.is_empty() {
+                    log::error!("save_dir: ${name} is not set");
+                }
+                out.push_str(&rest[pos..pos + 1 + len]);
+            }
+        }
+        rest = &after[len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn lookup_var(name: &str) -> Option<String> {
+    if let Some(value) = env::var(name).ok().filter(|v| !v.is_empty()) {
+        return Some(value);
+    }
+    let fallback = match name {
+        "HOME" => dirs::home_dir(),
+        "XDG_PICTURES_DIR" => {
+            dirs::picture_dir().or_else(|| dirs::home_dir().map(|home| home.join("Pictures")))
+        }
+        _ => None,
+    };
+    fallback.map(|p| p.display().to_string())
+}
+
+/// Recursively overlay `layer` onto `base`; nested tables merge, anything else replaces
+fn merge_tables(base: &mut toml::Table, layer: &toml::Table) {
+    for (key, value) in layer {
+        match (base.get_mut(key), value) {
+            (Some(toml::Value::Table(inner)), toml::Value::Table(overlay)) => {
+                merge_tables(inner, overlay)
+            }
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//! The capture history: an index of every saved screenshot, behind
+//! `razorshot history` and the gallery window.
+//!
+//! The index is a JSON array in `$XDG_DATA_HOME/razorshot/history.json`,
+//! oldest first, rewritten through a temporary file on every change. The
+//! tray and one-shot captures or uploads may change it at the same time, so
+//! changes take an `flock` on `history.json.lock` first.
+//! Thumbnails live in `$XDG_CACHE_HOME/razorshot/thumbnails`, named after
+//! a hash of the screenshot's path.
+
+use cairo::ImageSurface;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use crate::capture::CaptureInfo;
+use crate::config::Config;
+
+/// Longest side of a thumbnail, in pixels
+const THUMBNAIL_SIZE: i32 = 256;
+
+/// One saved screenshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub path: PathBuf,
+    /// Capture time, RFC 3339
+    pub time: String,
+    pub mode: String,
+    pub width: i32,
+    pub height: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload_url: Option<String>,
+}
+
+impl Entry {
+    /// Capture time as `2025-01-31 14:05:09` in the local time zone
+    pub fn local_time(&self) -> String {
+        DateTime::parse_from_rfc3339(&self.time)
+            .map(|time| time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|_| self.time.clone())
+    }
+
+    /// Whether the capture time starts with `query`, e.g. `2025`, `2025-01`
+    /// or `2025-01-31 14`. An empty query matches everything.
+    pub fn matches_date(&self, query: &str) -> bool {
+        self.local_time().starts_with(query.trim())
+    }
+}
+
+/// Add a freshly saved screenshot, with a thumbnail of `surface`
+pub fn record(config: &Config, info: &CaptureInfo, path: &Path, surface: &ImageSurface) {
+    if !config.history.enabled {
+        return;
+    }
+    let thumbnail = match write_thumbnail(path, surface) {
+        Ok(thumbnail) => Some(thumbnail),
+        Err(e) => {
+            log::warn!("No history thumbnail for {}: {}", path.display(), e);
+            None
+        }
+    };
+    let entry = Entry {
+        path: path.to_path_buf(),
+        time: info.time.to_rfc3339(),
+        mode: info.mode.name().to_string(),
+        width: surface.width(),
+        height: surface.height(),
+        thumbnail,
+        upload_url: None,
+    };
+    let limit = config.history.limit;
+    update(|entries| {
+        // Save As may replace a file that is already listed
+        entries.retain(|e| e.path != entry.path);
+        entries.push(entry);
+        let excess = entries.len().saturating_sub(limit);
+        for dropped in entries.drain(..excess) {
+            remove_thumbnail(&dropped);
+        }
+    });
+}
+
+/// Remember the link a screenshot was uploaded to
+pub fn set_upload_url(path: &Path, url: &str) {
+    update(|entries| {
+        for entry in entries.iter_mut().filter(|e| e.path == path) {
+            entry.upload_url = Some(url.to_string());
+        }
+    });
+}
+
+/// Forget a screenshot and its thumbnail. The file itself is left alone.
+pub fn remove(path: &Path) {
+    update(|entries| {
+        entries.retain(|entry| {
+            if entry.path == path {
+                remove_thumbnail(entry);
+                false
+            } else {
+                true
+            }
+        });
+    });
+}
+
+/// All entries, oldest first. A missing or unreadable index is empty.
+pub fn load() -> Vec<Entry> {
+    read_index().unwrap_or_else(|e| {
+        log::error!("{}", e);
+        Vec::new()
+    })
+}
+
+fn read_index() -> Result<Vec<Entry>, String> {
+    let path = index_path();
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Can't read {}: {}", path.display(), e)),
+    };
+    serde_json::from_str(&contents).map_err(|e| format!("{} is damaged: {}", path.display(), e))
+}
+
+fn update<F: FnOnce(&mut Vec<Entry>)>(change: F) {
+    // Held until the index is rewritten, so other processes wait their turn
+    let _lock = match lock_index() {
+        Ok(lock) => lock,
+        Err(e) => {
+            log::error!("Failed to lock the history: {}", e);
+            return;
+        }
+    };
+    let mut entries = match read_index() {
+        Ok(entries) => entries,
+        Err(e) => {
+            // Rewriting it would replace the whole history with one entry
+            let backup = index_path().with_extension("json.bak");
+            match fs::copy(index_path(), &backup) {
+                Ok(_) => log::error!("{}; not updating it (copy in {})", e, backup.display()),
+                Err(_) => log::error!("{}; not updating it", e),
+            }
+            return;
+        }
+    };
+    change(&mut entries);
+    if let Err(e) = save(&entries) {
+        log::error!("Failed to update the history: {}", e);
+    }
+}
+
+/// Take the exclusive lock on the index; it's released when the file is closed
+fn lock_index() -> std::io::Result<fs::File> {
+    let path = index_path().with_extension("json.lock");
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)?;
+    // SAFETY: the descriptor stays open for the duration of the call
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(file)
+}
+
+fn save(entries: &[Entry]) -> Result<(), Box<dyn std::error::Error>> {
+    let path = index_path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Write then rename, so a crash never leaves half an index. The temporary
+    // name is per process, so two writers never share one.
+    let temp = path.with_extension(format!("json.{}.tmp", std::process::id()));
+    fs::write(&temp, serde_json::to_string_pretty(entries)?)?;
+    fs::rename(&temp, &path)?;
+    Ok(())
+}
+
+fn index_path() -> PathBuf {
+    glib::user_data_dir().join("razorshot").join("history.json")
+}
+
+/// Scale `surface` down to fit `THUMBNAIL_SIZE` and write it as a PNG
+fn write_thumbnail(
+    path: &Path,
+    surface: &ImageSurface,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let dir = glib::user_cache_dir().join("razorshot").join("thumbnails");
+    fs::create_dir_all(&dir)?;
+    let hash: String = Sha256::digest(path.to_string_lossy().as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    let thumbnail = dir.join(format!("{hash}.png"));
+
+    let scale = (THUMBNAIL_SIZE as f64 / surface.width().max(surface.height()) as f64).min(1.0);
+    let width = ((surface.width() as f64 * scale).round() as i32).max(1);
+    let height = ((surface.height() as f64 * scale).round() as i32).max(1);
+    let small = ImageSurface::create(cairo::Format::ARgb32, width, height)?;
+    let cr = cairo::Context::new(&small)?;
+    cr.scale(scale, scale);
+    cr.set_source_surface(surface, 0.0, 0.0)?;
+    cr.source().set_filter(cairo::Filter::Good);
+    cr.paint()?;
+    drop(cr);
+
+    let mut file = fs::File::create(&thumbnail)?;
+    small.write_to_png(&mut file)?;
+    Ok(thumbnail)
+}
+
+fn remove_thumbnail(entry: &Entry) {
+    if let Some(thumbnail) = &entry.thumbnail {
+        let _ = fs::remove_file(thumbnail);
+    }
+}
@@ -3,38 +3,198 @@ mod app;
 mod capture;
 mod cli;
 mod config;
+mod history;
 mod output;
 mod tray;
 mod ui;
 
 use clap::Parser;
-use cli::{Cli, Command};
-use config::Config;
+use cli::{Cli, Command, ConfigAction};
+use config::{Config, DefaultAction, LoadOptions};
+
+/// Load the config with the profile and overrides from the command line,
+/// exiting on an unknown profile or invalid override.
+fn load_config(options: &LoadOptions) -> Config {
+    match Config::load_with(options) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
+}
 
 fn main() {
     env_logger::init();
 
     let cli = Cli::parse();
 
+    if let Some(path) = &cli.config {
+        Config::set_config_path(path.clone());
+    }
+
+    let mut options = LoadOptions::from_env().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+    if cli.profile.is_some() {
+        options.profile = cli.profile.clone();
+    }
+    if let Some(color) = &cli.color {
+        options.set("annotation.default_color", color.to_hex());
+    }
+    if cli.strip_metadata {
+        options.set("metadata.strip", true);
+    }
+
+    let (clipboard, upload) = match &cli.command {
+        Some(Command::Region { clipboard, upload, .. })
+        | Some(Command::Full { clipboard, upload, .. })
+        | Some(Command::Tray { clipboard, upload }) => (*clipboard, *upload),
+        _ => (None, false),
+    };
+    if let Some(mode) = clipboard {
+        options.set("behavior.clipboard_mode", mode.name());
+    }
+    if upload {
+        options.set("upload.auto", true);
+    }
+
     let action = match cli.command {
-        Some(Command::Full { no_edit, monitor }) => {
+        Some(Command::Full { no_edit, monitor, .. }) => {
             if no_edit {
                 app::AppAction::FullNoEdit { monitor }
             } else {
                 app::AppAction::FullEdit { monitor }
             }
         }
-        Some(Command::Region { no_edit }) => {
+        Some(Command::Region { no_edit, .. }) => {
             if no_edit {
                 app::AppAction::RegionNoEdit
             } else {
                 app::AppAction::RegionEdit
             }
         }
-        Some(Command::Tray) => app::AppAction::Tray,
-        Some(Command::Config { show, save_dir }) => {
+        Some(Command::Tray { .. }) => app::AppAction::Tray,
+        Some(Command::Edit { .. }) => app::AppAction::EditClipboard,
+        Some(Command::Upload { file }) => {
+            let config = load_config(&options);
+            let values = output::filename::TemplateValues::for_file(&config, &file);
+            match output::upload::upload_file(&config, &file, &values) {
+                Ok(url) => {
+                    println!("{url}");
+                    // The history lists absolute paths
+                    history::set_upload_url(&std::path::absolute(&file).unwrap_or(file), &url);
+                    if let Err(e) = output::clipboard::copy_text_to_clipboard(&url) {
+                        log::error!("Failed to copy upload URL: {}", e);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::History { date, limit, json }) => {
+            let entries: Vec<_> = history::load()
+                .into_iter()
+                .rev()
+                .filter(|entry| date.as_deref().is_none_or(|date| entry.matches_date(date)))
+                .take(limit.unwrap_or(usize::MAX))
+                .collect();
+            if json {
+                println!("{}", serde_json::to_string_pretty(&entries).unwrap_or_default());
+                return;
+            }
+            if entries.is_empty() {
+                eprintln!("No screenshots in the history");
+            }
+            for entry in entries {
+                let size = format!("{}x{}", entry.width, entry.height);
+                let mut line = format!(
+                    "{}  {size:>9}  {:<9}  {}",
+                    entry.local_time(),
+                    entry.mode,
+                    entry.path.display()
+                );
+                if let Some(url) = &entry.upload_url {
+                    line.push_str(&format!("  {url}"));
+                }
+                if !entry.path.exists() {
+                    line.push_str("  (deleted)");
+                }
+                println!("{line}");
+            }
+            return;
+        }
+        Some(Command::Gallery) => app::AppAction::Gallery,
+        Some(Command::PickColor) => {
+            match capture::portal::pick_color() {
+                Ok(color) => println!("{}", color.to_hex()),
+                Err(e) => {
+                    eprintln!("Failed to pick color: {e}");
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::ServeClipboard { path, uri, mime }) => {
+            if let Err(e) = output::clipboard::serve(&mime, path.as_deref(), uri.as_deref()) {
+                log::error!("Clipboard server failed: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Config {
+            show,
+            check,
+            save_dir,
+            action,
+        }) => {
+            if let Some(action) = action {
+                let path = Config::config_path();
+                // With --profile, writes go to that profile's table
+                let scoped = |key: String| match &cli.profile {
+                    Some(profile) => format!("profiles.{profile}.{key}"),
+                    None => key,
+                };
+                let result = match action {
+                    ConfigAction::Get { key } => {
+                        config::edit::get(&load_config(&options), &key)
+                            .map(|value| println!("{value}"))
+                    }
+                    ConfigAction::Set { key, value } => {
+                        config::edit::set(&path, &scoped(key), &value)
+                    }
+                    ConfigAction::Unset { key } => config::edit::unset(&path, &scoped(key)),
+                    ConfigAction::Reset { key } => {
+                        config::edit::reset(&path, key.map(scoped).as_deref())
+                    }
+                };
+                if let Err(e) = result {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                }
+                return;
+            }
+            if check {
+                let path = Config::config_path();
+                if !path.exists() {
+                    println!("{} does not exist; defaults will be used", path.display());
+                    return;
+                }
+                match Config::read(&path) {
+                    Ok(_) => println!("{} is valid", path.display()),
+                    Err(e) => {
+                        eprintln!("{e}");
+                        std::process::exit(1);
+                    }
+                }
+                return;
+            }
             if show {
-                let config = Config::load();
+                let config = load_config(&options);
                 println!(
                     "{}",
                     toml::to_string_pretty(&config).unwrap_or_else(|_| "Error".into())
@@ -42,9 +202,12 @@ fn main() {
                 return;
             }
             if let Some(dir) = save_dir {
-                let mut config = Config::load();
-                config.save_dir = dir;
-                config.save();
+                // Same as `config set save_dir`, which keeps comments and key order
+                if let Err(e) = config::edit::set(&Config::config_path(), "save_dir", &dir) {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                }
+                let config = load_config(&options);
                 println!("Save directory updated. Current config:");
                 println!(
                     "{}",
@@ -53,7 +216,7 @@ fn main() {
                 return;
             }
             // No flags → show config
-            let config = Config::load();
+            let config = load_config(&options);
             println!(
                 "{}",
                 toml::to_string_pretty(&config).unwrap_or_else(|_| "Error".into())
@@ -62,14 +225,15 @@ fn main() {
         }
         None => {
             // Default action from config
-            let config = Config::load();
-            match config.behavior.default_action.as_str() {
-                "region" => app::AppAction::RegionEdit,
-                "full" => app::AppAction::FullEdit { monitor: None },
-                _ => app::AppAction::Tray,
+            let config = load_config(&options);
+            match config.behavior.default_action {
+                DefaultAction::Region => app::AppAction::RegionEdit,
+                DefaultAction::Full => app::AppAction::FullEdit { monitor: None },
+                DefaultAction::Tray => app::AppAction::Tray,
             }
         }
     };
 
-    app::run(action);
+    let config = load_config(&options);
+    app::run(action, config, options);
 }
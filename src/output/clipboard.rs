@@ -1,13 +1,60 @@
 use cairo::ImageSurface;
-use std::process::Command;
+use std::ffi::OsString;
+use std::io::{Read, Write};
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
 
-/// Copy an ImageSurface to the system clipboard.
-/// Tries arboard first, falls back to wl-copy.
-pub fn copy_to_clipboard(surface: &ImageSurface) -> Result<(), Box<dyn std::error::Error>> {
+use super::metadata::xml_escape;
+use crate::config::ClipboardMode;
+
+/// Name of the hidden subcommand that serves the clipboard after razorshot exits
+pub const SERVE_COMMAND: &str = "__serve-clipboard";
+
+const PNG_MIME: &str = "image/png";
+const TEXT_MIME: &str = "text/plain";
+const HTML_MIME: &str = "text/html";
+
+/// How long the clipboard server gets to take the selection
+const READY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Copy a screenshot to the system clipboard in the configured `mode`.
+/// `saved` is the file it was saved to, if saving succeeded.
+///
+/// A detached `razorshot __serve-clipboard` process keeps serving the contents
+/// after this process exits, until another client takes the clipboard. In
+/// `image` mode it offers `image/png`, plus `text/uri-list` and `text/plain`
+/// for the saved file. If it can't be started, falls back to arboard, then
+/// wl-copy, with a single type; the same happens if it doesn't report that it
+/// owns the clipboard within `READY_TIMEOUT`.
+pub fn copy_to_clipboard(
+    surface: &ImageSurface,
+    saved: Option<&Path>,
+    mode: ClipboardMode,
+) -> Result<(), Box<dyn std::error::Error>> {
     // Get PNG bytes first (doesn't require mutable access)
     let mut png_data = Vec::new();
     surface.write_to_png(&mut png_data)?;
 
+    if let Some((mime, text)) = text_payload(mode, &png_data, saved)? {
+        return copy_text(mime, &text);
+    }
+
+    let mut args: Vec<OsString> = Vec::new();
+    if let Some(path) = saved {
+        args.extend(["--path".into(), path.into()]);
+        args.extend(["--uri".into(), glib::filename_to_uri(path, None)?.as_str().into()]);
+    }
+    match spawn_server(PNG_MIME, &png_data, args) {
+        Ok(()) => {
+            log::info!("Clipboard handed to a background server");
+            return Ok(());
+        }
+        Err(e) => log::warn!("Clipboard server failed ({}), copying in-process", e),
+    }
+
     match copy_via_arboard(surface) {
         Ok(()) => {
             log::info!("Image copied to clipboard via arboard");
@@ -15,11 +62,230 @@ pub fn copy_to_clipboard(surface: &ImageSurface) -> Result<(), Box<dyn std::erro
         }
         Err(e) => {
             log::warn!("arboard failed ({}), trying wl-copy fallback", e);
-            copy_via_wl_copy_png(&png_data)
+            copy_via_wl_copy(PNG_MIME, &png_data)
+        }
+    }
+}
+
+/// The text to copy and its MIME type, or None to copy the image
+fn text_payload(
+    mode: ClipboardMode,
+    png_data: &[u8],
+    saved: Option<&Path>,
+) -> Result<Option<(&'static str, String)>, Box<dyn std::error::Error>> {
+    if mode == ClipboardMode::Image {
+        return Ok(None);
+    }
+    if mode == ClipboardMode::DataUri {
+        let uri = format!("data:{PNG_MIME};base64,{}", glib::base64_encode(png_data));
+        return Ok(Some((TEXT_MIME, uri)));
+    }
+    let Some(path) = saved else {
+        log::warn!("No saved file for clipboard mode \"{}\"; copying the image", mode.name());
+        return Ok(None);
+    };
+    let uri = glib::filename_to_uri(path, None)?;
+    Ok(Some(match mode {
+        ClipboardMode::Path => (TEXT_MIME, path.to_string_lossy().into_owned()),
+        ClipboardMode::Uri => (TEXT_MIME, uri.to_string()),
+        ClipboardMode::Markdown => {
+            let target = path.to_string_lossy();
+            // Angle brackets keep spaces and parentheses in the path intact
+            if target.contains([' ', '(', ')']) {
+                (TEXT_MIME, format!("![screenshot](<{target}>)"))
+            } else {
+                (TEXT_MIME, format!("![screenshot]({target})"))
+            }
+        }
+        ClipboardMode::Html => (
+            HTML_MIME,
+            format!("<img src=\"{}\" alt=\"screenshot\">", xml_escape(&uri)),
+        ),
+        ClipboardMode::Image | ClipboardMode::DataUri => unreachable!(),
+    }))
+}
+
+/// Put plain text on the clipboard, e.g. a hook's output
+pub fn copy_text_to_clipboard(text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    copy_text(TEXT_MIME, text)
+}
+
+fn copy_text(mime: &str, text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    match spawn_server(mime, text.as_bytes(), Vec::new()) {
+        Ok(()) => {
+            log::info!("Clipboard handed to a background server");
+            return Ok(());
+        }
+        Err(e) => log::warn!("Clipboard server failed ({}), copying in-process", e),
+    }
+
+    let result = arboard::Clipboard::new().and_then(|mut clipboard| {
+        if mime == HTML_MIME {
+            clipboard.set_html(text, Some(text))
+        } else {
+            clipboard.set_text(text)
+        }
+    });
+    match result {
+        Ok(()) => {
+            log::info!("Text copied to clipboard via arboard");
+            Ok(())
+        }
+        Err(e) => {
+            log::warn!("arboard failed ({}), trying wl-copy fallback", e);
+            copy_via_wl_copy(mime, text.as_bytes())
         }
     }
 }
 
+/// Start the detached clipboard server, hand it the data over stdin and wait
+/// for the byte on its stdout that says it owns the clipboard
+fn spawn_server(
+    mime: &str,
+    data: &[u8],
+    args: Vec<OsString>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut child = Command::new(std::env::current_exe()?)
+        .arg(SERVE_COMMAND)
+        .arg("--mime")
+        .arg(mime)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        // Own process group, so closing the terminal or Ctrl+C doesn't end it
+        .process_group(0)
+        .spawn()?;
+
+    let mut stdin = child.stdin.take().ok_or("no stdin for clipboard server")?;
+    stdin.write_all(data)?;
+    drop(stdin);
+
+    let mut stdout = child.stdout.take().ok_or("no stdout for clipboard server")?;
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut byte = [0u8; 1];
+        // 0 bytes means it exited without taking the clipboard
+        let _ = tx.send(matches!(stdout.read(&mut byte), Ok(1)));
+    });
+    let ready = rx.recv_timeout(READY_TIMEOUT);
+    if ready != Ok(true) {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(match ready {
+            Ok(_) => "it exited without taking the clipboard".into(),
+            Err(_) => "it didn't take the clipboard in time".into(),
+        });
+    }
+
+    // Reap it when it exits, which may be long after we hand over
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
+/// Tell `spawn_server` that this process owns the clipboard now
+fn report_ready() -> std::io::Result<()> {
+    let mut stdout = std::io::stdout();
+    stdout.write_all(b"\n")?;
+    stdout.flush()
+}
+
+/// Body of `razorshot __serve-clipboard`: read data of type `mime` from stdin
+/// and serve it until another client takes the clipboard. A PNG is offered
+/// along with the file's path and URI, text also as `text/plain`.
+///
+/// Writes a byte to stdout once it owns the clipboard, for `spawn_server`.
+pub fn serve(
+    mime: &str,
+    path: Option<&Path>,
+    uri: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut data = Vec::new();
+    std::io::stdin().read_to_end(&mut data)?;
+
+    match prepare_wayland(mime, &data, path, uri) {
+        Ok(copy) => {
+            report_ready()?;
+            copy.serve()?;
+            Ok(())
+        }
+        Err(e) => {
+            // No data-control protocol (X11, or an older compositor)
+            log::warn!("Wayland clipboard unavailable ({}), serving via arboard", e);
+            serve_arboard(mime, data)
+        }
+    }
+}
+
+/// Take the Wayland clipboard; serving it is left to the caller
+fn prepare_wayland(
+    mime: &str,
+    data: &[u8],
+    path: Option<&Path>,
+    uri: Option<&str>,
+) -> Result<wl_clipboard_rs::copy::PreparedCopy, Box<dyn std::error::Error>> {
+    use wl_clipboard_rs::copy::{MimeSource, MimeType, Options, Source};
+
+    let mut sources = Vec::new();
+    if mime != TEXT_MIME {
+        sources.push(MimeSource {
+            source: Source::Bytes(data.into()),
+            mime_type: MimeType::Specific(mime.into()),
+        });
+    }
+    if let Some(uri) = uri {
+        sources.push(MimeSource {
+            source: Source::Bytes(format!("{uri}\r\n").into_bytes().into()),
+            mime_type: MimeType::Specific("text/uri-list".into()),
+        });
+    }
+    let text = match path {
+        Some(path) => Some(path.to_string_lossy().into_owned().into_bytes()),
+        None if mime.starts_with("text/") => Some(data.to_vec()),
+        None => None,
+    };
+    if let Some(text) = text {
+        sources.push(MimeSource {
+            source: Source::Bytes(text.into()),
+            mime_type: MimeType::Text,
+        });
+    }
+
+    let mut options = Options::new();
+    // Serve from this process; serve() returns once another client owns the clipboard
+    options.foreground(true);
+    Ok(options.prepare_copy_multi(sources)?)
+}
+
+fn serve_arboard(mime: &str, data: Vec<u8>) -> Result<(), Box<dyn std::error::Error>> {
+    use arboard::{Clipboard, ImageData, SetExtLinux};
+
+    let mut clipboard = Clipboard::new()?;
+    // wait() blocks until another client takes ownership, so there is no
+    // point after taking it to report from; an open clipboard and valid data
+    // have to do
+    if mime == PNG_MIME {
+        let img = image::load_from_memory_with_format(&data, image::ImageFormat::Png)?.to_rgba8();
+        let (width, height) = img.dimensions();
+        report_ready()?;
+        clipboard.set().wait().image(ImageData {
+            width: width as usize,
+            height: height as usize,
+            bytes: std::borrow::Cow::Owned(img.into_raw()),
+        })?;
+    } else {
+        let text = String::from_utf8(data)?;
+        report_ready()?;
+        if mime == HTML_MIME {
+            clipboard.set().wait().html(text.as_str(), Some(text.as_str()))?;
+        } else {
+            clipboard.set().wait().text(text)?;
+        }
+    }
+    Ok(())
+}
+
 fn copy_via_arboard(surface: &ImageSurface) -> Result<(), Box<dyn std::error::Error>> {
     use arboard::{Clipboard, ImageData};
 
@@ -69,16 +335,15 @@ fn clone_surface(src: &ImageSurface) -> Result<ImageSurface, Box<dyn std::error:
     Ok(dest)
 }
 
-fn copy_via_wl_copy_png(png_data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+fn copy_via_wl_copy(mime: &str, data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
     let mut child = Command::new("wl-copy")
         .arg("--type")
-        .arg("image/png")
-        .stdin(std::process::Stdio::piped())
+        .arg(mime)
+        .stdin(Stdio::piped())
         .spawn()?;
 
     if let Some(ref mut stdin) = child.stdin {
-        use std::io::Write;
-        stdin.write_all(png_data)?;
+        stdin.write_all(data)?;
     }
 
     let status = child.wait()?;
@@ -86,6 +351,6 @@ fn copy_via_wl_copy_png(png_data: &[u8]) -> Result<(), Box<dyn std::error::Error
         return Err("wl-copy failed".into());
     }
 
-    log::info!("Image copied to clipboard via wl-copy");
+    log::info!("Copied to clipboard via wl-copy");
     Ok(())
 }
@@ -0,0 +1,57 @@
+//! Data offered when the screenshot is dragged out of the editor: the image
+//! written to a temporary file (`text/uri-list`) and its bytes (`image/png`).
+
+use cairo::ImageSurface;
+use glib::prelude::*;
+use std::fs;
+use std::path::PathBuf;
+
+/// Largest side of the preview shown under the pointer while dragging
+const ICON_SIZE: f64 = 160.0;
+
+/// Where dragged files are written. It is emptied on every drag, so only the
+/// latest file is kept; drop targets read it when the drop happens.
+fn drag_dir() -> PathBuf {
+    glib::user_runtime_dir().join("razorshot").join("drag")
+}
+
+/// Write `surface` to a fresh temporary PNG and return the drag content
+pub fn content(surface: &ImageSurface) -> Result<gdk4::ContentProvider, Box<dyn std::error::Error>> {
+    let mut png_data = Vec::new();
+    surface.write_to_png(&mut png_data)?;
+
+    let dir = drag_dir();
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let name = chrono::Local::now().format("Screenshot_%Y-%m-%d_%H-%M-%S.png").to_string();
+    let path = dir.join(name);
+    fs::write(&path, &png_data)?;
+    log::debug!("Dragging {}", path.display());
+
+    let file = gio::File::for_path(&path);
+    Ok(gdk4::ContentProvider::new_union(&[
+        gdk4::ContentProvider::for_value(&file.to_value()),
+        gdk4::ContentProvider::for_bytes("image/png", &glib::Bytes::from_owned(png_data)),
+    ]))
+}
+
+/// A thumbnail of `surface` to use as the drag icon
+pub fn icon(surface: &ImageSurface) -> Result<gdk4::Texture, Box<dyn std::error::Error>> {
+    let scale = (ICON_SIZE / surface.width() as f64)
+        .min(ICON_SIZE / surface.height() as f64)
+        .min(1.0);
+    let thumb = ImageSurface::create(
+        cairo::Format::ARgb32,
+        ((surface.width() as f64 * scale).ceil() as i32).max(1),
+        ((surface.height() as f64 * scale).ceil() as i32).max(1),
+    )?;
+    let cr = cairo::Context::new(&thumb)?;
+    cr.scale(scale, scale);
+    cr.set_source_surface(surface, 0.0, 0.0)?;
+    cr.paint()?;
+    drop(cr);
+
+    let mut png_data = Vec::new();
+    thumb.write_to_png(&mut png_data)?;
+    Ok(gdk4::Texture::from_bytes(&glib::Bytes::from_owned(png_data))?)
+}
@@ -1,66 +1,176 @@
 use cairo::ImageSurface;
-use chrono::Local;
 use image::ImageEncoder;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::config::Config;
+use crate::annotate::shapes::Shape;
+use crate::capture::CaptureInfo;
+use crate::config::{Config, ExportConfig, ExportFormat};
+use crate::output::filename::{self, TemplateValues};
+use crate::output::metadata::{self, Metadata};
+use crate::output::png_encoder;
 
 /// Save an ImageSurface to the configured save directory.
-/// Supports PNG and JPEG formats based on config.export_format.
+/// The format (PNG, JPEG, WebP, QOI, BMP or TIFF) comes from config.export_format.
+/// The name comes from `filename_template`; existing files are never overwritten.
+/// PNG and JPEG files get the metadata enabled in `[metadata]`, which may
+/// include the annotation `shapes`. The file is added to the capture history.
 /// Returns the path of the saved file.
 pub fn save_screenshot(
     surface: &ImageSurface,
     config: &Config,
+    info: &CaptureInfo,
+    shapes: &[Shape],
 ) -> Result<PathBuf, Box<dyn std::error::Error>> {
     let save_dir = config.resolve_save_dir();
-    fs::create_dir_all(&save_dir)?;
-
-    let ext = match config.export_format.to_lowercase().as_str() {
-        "jpg" | "jpeg" => "jpg",
-        _ => "png",
-    };
-
-    let basename = Local::now().format(&config.filename_template).to_string();
-    let filename = format!("{basename}.{ext}");
-    let path = save_dir.join(&filename);
-
-    match ext {
-        "jpg" => save_as_jpeg(surface, &path, config.annotation.jpeg_quality)?,
-        _ => {
-            let mut file = fs::File::create(&path)?;
-            surface.write_to_png(&mut file)?;
-        }
+    let values = TemplateValues::new(config, info, surface.width(), surface.height());
+    let path = filename::reserve_path(
+        &save_dir,
+        &config.filename_template,
+        config.export_format.extension(),
+        &values,
+    )?;
+
+    let metadata = Metadata::new(&config.metadata, info, shapes);
+    if let Err(e) = write_image(surface, &path, config.export_format, config, &metadata) {
+        // Don't leave the empty placeholder behind
+        let _ = fs::remove_file(&path);
+        return Err(e);
     }
 
     log::info!("Screenshot saved to: {}", path.display());
+    crate::history::record(config, info, &path, surface);
     Ok(path)
 }
 
+/// Save an ImageSurface to a path the user picked, in `format`, replacing
+/// any existing file. Encoder settings and metadata are as for `save_screenshot`.
+pub fn save_screenshot_as(
+    surface: &ImageSurface,
+    path: &Path,
+    format: ExportFormat,
+    config: &Config,
+    info: &CaptureInfo,
+    shapes: &[Shape],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let metadata = Metadata::new(&config.metadata, info, shapes);
+    write_image(surface, path, format, config, &metadata)?;
+    log::info!("Screenshot saved to: {}", path.display());
+    crate::history::record(config, info, path, surface);
+    Ok(())
+}
+
+fn write_image(
+    surface: &ImageSurface,
+    path: &Path,
+    format: ExportFormat,
+    config: &Config,
+    metadata: &Metadata,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match format {
+        ExportFormat::Jpeg => save_as_jpeg(surface, path, config.export.jpeg_quality, metadata)?,
+        ExportFormat::Png => png_encoder::write_png(
+            path,
+            &rgba_pixels(surface)?,
+            surface.width() as u32,
+            surface.height() as u32,
+            &config.export,
+            &metadata.png_text(),
+        )?,
+        ExportFormat::Webp => save_as_webp(surface, path, &config.export)?,
+        ExportFormat::Qoi => save_with_image(surface, path, image::ImageFormat::Qoi)?,
+        ExportFormat::Bmp => save_with_image(surface, path, image::ImageFormat::Bmp)?,
+        ExportFormat::Tiff => save_with_image(surface, path, image::ImageFormat::Tiff)?,
+    }
+    Ok(())
+}
+
+/// Copy a surface so its pixel data can be borrowed mutably
+fn clone_surface(surface: &ImageSurface) -> Result<ImageSurface, cairo::Error> {
+    let dest = ImageSurface::create(cairo::Format::ARgb32, surface.width(), surface.height())?;
+    let cr = cairo::Context::new(&dest)?;
+    cr.set_source_surface(surface, 0.0, 0.0)?;
+    cr.paint()?;
+    drop(cr);
+    dest.flush();
+    Ok(dest)
+}
+
+/// Convert Cairo's premultiplied BGRA to straight RGBA
+fn rgba_pixels(surface: &ImageSurface) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let width = surface.width() as usize;
+    let height = surface.height() as usize;
+    let mut cloned = clone_surface(surface)?;
+    let stride = cloned.stride() as usize;
+    let data = cloned.data()?;
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        for x in 0..width {
+            let offset = y * stride + x * 4;
+            let a = data[offset + 3];
+            let unpremultiply = |c: u8| {
+                if a == 0 {
+                    0
+                } else {
+                    ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8
+                }
+            };
+            rgba.push(unpremultiply(data[offset + 2]));
+            rgba.push(unpremultiply(data[offset + 1]));
+            rgba.push(unpremultiply(data[offset]));
+            rgba.push(a);
+        }
+    }
+    Ok(rgba)
+}
+
+/// Save through one of the `image` crate's RGBA encoders (QOI, BMP, TIFF)
+fn save_with_image(
+    surface: &ImageSurface,
+    path: &Path,
+    format: image::ImageFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let rgba = rgba_pixels(surface)?;
+    image::save_buffer_with_format(
+        path,
+        &rgba,
+        surface.width() as u32,
+        surface.height() as u32,
+        image::ExtendedColorType::Rgba8,
+        format,
+    )?;
+    Ok(())
+}
+
+fn save_as_webp(
+    surface: &ImageSurface,
+    path: &Path,
+    export: &ExportConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let rgba = rgba_pixels(surface)?;
+    let encoder = webp::Encoder::from_rgba(&rgba, surface.width() as u32, surface.height() as u32);
+    let data = encoder
+        .encode_simple(export.webp_lossless, export.webp_quality as f32)
+        .map_err(|e| format!("WebP encoding failed: {e:?}"))?;
+    fs::write(path, &*data)?;
+    Ok(())
+}
+
 fn save_as_jpeg(
     surface: &ImageSurface,
-    path: &PathBuf,
+    path: &Path,
     quality: u8,
+    metadata: &Metadata,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let width = surface.width() as u32;
     let height = surface.height() as u32;
     let stride = surface.stride() as usize;
 
     // Clone the surface to get mutable access to pixel data
-    let mut cloned = {
-        let w = surface.width();
-        let h = surface.height();
-        let dest = ImageSurface::create(cairo::Format::ARgb32, w, h)?;
-        let cr = cairo::Context::new(&dest)?;
-        cr.set_source_surface(surface, 0.0, 0.0)?;
-        cr.paint()?;
-        drop(cr);
-        dest.flush();
-        dest
-    };
+    let mut cloned = clone_surface(surface)?;
 
     let data = cloned.data()?;
-
     // Convert Cairo BGRA to RGB (JPEG doesn't support alpha)
     let mut rgb = Vec::with_capacity((width * height * 3) as usize);
     for y in 0..height as usize {
@@ -75,10 +185,17 @@ fn save_as_jpeg(
         }
     }
 
-    let file = fs::File::create(path)?;
-    let mut buf_writer = std::io::BufWriter::new(file);
-    let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buf_writer, quality);
+    let mut jpeg = Vec::new();
+    let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, quality);
+    if let Some(exif) = metadata.exif() {
+        encoder.set_exif_metadata(exif)?;
+    }
     encoder.write_image(&rgb, width, height, image::ExtendedColorType::Rgb8)?;
 
+    if let Some(xmp) = metadata.xmp() {
+        metadata::insert_jpeg_xmp(&mut jpeg, &xmp)?;
+    }
+    fs::write(path, jpeg)?;
+
     Ok(())
 }
@@ -0,0 +1,204 @@
+//! Turn `filename_template` into a path inside the save directory.
+//!
+//! The template is strftime (`%Y-%m-%d`) plus `{token}` placeholders, and
+//! each `/` starts a subdirectory. Every path component is sanitized, and an
+//! existing file is never overwritten: `{counter}` counts up, otherwise a
+//! `-2`, `-3`, ... suffix is added.
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::capture::{CaptureInfo, CaptureMode};
+use crate::config::Config;
+
+/// Placeholders understood in `filename_template`
+pub const TOKENS: [&str; 7] = [
+    "counter", "width", "height", "mode", "monitor", "profile", "hostname",
+];
+
+/// Characters that are replaced in file and directory names. Linux only forbids
+/// `/`, but screenshots often end up on shared or FAT drives.
+const ILLEGAL_CHARS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Highest `{counter}` or collision suffix tried before giving up
+const MAX_ATTEMPTS: u32 = 100_000;
+
+/// Values for the template tokens of one screenshot
+pub struct TemplateValues {
+    pub width: i32,
+    pub height: i32,
+    pub mode: &'static str,
+    pub monitor: Option<u32>,
+    pub profile: Option<String>,
+    pub hostname: String,
+}
+
+impl TemplateValues {
+    pub fn new(config: &Config, info: &CaptureInfo, width: i32, height: i32) -> Self {
+        Self {
+            width,
+            height,
+            mode: info.mode.name(),
+            monitor: info.monitor,
+            profile: config.active_profile.clone(),
+            hostname: glib::host_name().to_string(),
+        }
+    }
+
+    /// Values for an existing file, e.g. one given to `razorshot upload`.
+    /// `{mode}` is `file`; the size is 0 when the image can't be read.
+    pub fn for_file(config: &Config, path: &Path) -> Self {
+        let (width, height) = image::image_dimensions(path).unwrap_or((0, 0));
+        Self {
+            width: width as i32,
+            height: height as i32,
+            mode: CaptureMode::File.name(),
+            monitor: None,
+            profile: config.active_profile.clone(),
+            hostname: glib::host_name().to_string(),
+        }
+    }
+
+    fn get(&self, token: &str, counter: u32) -> Option<String> {
+        Some(match token {
+            "counter" => format!("{counter:03}"),
+            "width" => self.width.to_string(),
+            "height" => self.height.to_string(),
+            "mode" => self.mode.to_string(),
+            "monitor" => self.monitor.map_or("all".to_string(), |m| m.to_string()),
+            "profile" => self.profile.clone().unwrap_or_else(|| "default".into()),
+            "hostname" => self.hostname.clone(),
+            _ => return None,
+        })
+    }
+}
+
+/// Check a template for unknown tokens and invalid strftime specifiers
+pub fn check_template(template: &str) -> Result<(), String> {
+    if StrftimeItems::new(template).any(|item| matches!(item, Item::Error)) {
+        return Err(format!("invalid strftime format in \"{template}\""));
+    }
+    for token in tokens(template) {
+        if !TOKENS.contains(&token) {
+            return Err(format!(
+                "unknown token {{{token}}}; available tokens: {}",
+                TOKENS.map(|t| format!("{{{t}}}")).join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Render the template for a new screenshot and create the file empty, so
+/// concurrent captures can't pick the same name. Missing subdirectories are
+/// created. Returns the path to write the image to.
+pub fn reserve_path(
+    save_dir: &Path,
+    template: &str,
+    extension: &str,
+    values: &TemplateValues,
+) -> Result<PathBuf, String> {
+    check_template(template)?;
+    let now = Local::now();
+    let uses_counter = tokens(template).any(|t| t == "counter");
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        let counter = if uses_counter { attempt } else { 1 };
+        let mut components = render(template, &now, values, counter);
+        let name = components.pop().unwrap_or_default();
+        let name = if name.is_empty() { "Screenshot".to_string() } else { name };
+        let name = if uses_counter || attempt == 1 {
+            format!("{name}.{extension}")
+        } else {
+            format!("{name}-{attempt}.{extension}")
+        };
+
+        let dir = components.iter().fold(save_dir.to_path_buf(), |dir, c| dir.join(c));
+        fs::create_dir_all(&dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+        let path = dir.join(name);
+        match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(_) => return Ok(path),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("{}: {e}", path.display())),
+        }
+    }
+    Err(format!("no free filename for \"{template}\" in {}", save_dir.display()))
+}
+
+/// Render a template as a `/`-separated prefix, e.g. an object storage key
+/// prefix. Empty components are dropped and `{counter}` is always `001`.
+pub fn render_prefix(template: &str, values: &TemplateValues) -> String {
+    render(template, &Local::now(), values, 1)
+        .into_iter()
+        .filter(|component| !component.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Expand the template into sanitized path components; the last one is the
+/// file name without extension. Empty components are dropped.
+fn render(template: &str, now: &DateTime<Local>, values: &TemplateValues, counter: u32) -> Vec<String> {
+    let parts: Vec<&str> = template.split('/').collect();
+    let last = parts.len() - 1;
+    parts
+        .iter()
+        .enumerate()
+        .filter_map(|(i, part)| {
+            let mut formatted = String::new();
+            let _ = write!(formatted, "{}", now.format_with_items(StrftimeItems::new(part)));
+            let component = sanitize(&substitute(&formatted, values, counter));
+            (i == last || !component.is_empty()).then_some(component)
+        })
+        .collect()
+}
+
+/// Replace `{token}`s with their values. Unknown tokens are left as written.
+fn substitute(text: &str, values: &TemplateValues, counter: u32) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}').and_then(|end| Some((end, values.get(&after[..end], counter)?))) {
+            Some((end, value)) => {
+                out.push_str(&value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Names inside `{...}` in the template
+fn tokens(template: &str) -> impl Iterator<Item = &str> {
+    template.split('{').skip(1).filter_map(|s| s.split_once('}').map(|(token, _)| token))
+}
+
+/// Make a single file or directory name safe: no separators, reserved or
+/// control characters, no surrounding whitespace and no `.`/`..`.
+fn sanitize(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| {
+            if ILLEGAL_CHARS.contains(&c) || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let cleaned = cleaned.trim().trim_end_matches('.').trim_end();
+    match cleaned {
+        "" if !name.trim().is_empty() => "_".to_string(),
+        _ => cleaned.to_string(),
+    }
+}
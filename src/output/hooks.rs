@@ -0,0 +1,223 @@
+//! `[hooks]` commands: user pipelines (upload, OCR, ticketing...) run after a
+//! capture or a save.
+//!
+//! A hook is a `sh -c` command line. It learns about the screenshot in three
+//! ways: `{path}`, `{mode}`, `{monitor}`, `{width}`, `{height}`, `{time}` and
+//! `{profile}` in the command are replaced with shell-quoted values, the same
+//! values are in `RAZORSHOT_HOOK_<NAME>` variables, and a JSON object with them
+//! and the `event` is written to its stdin. Hooks run on a background thread
+//! and are killed after `hooks.timeout` seconds, along with everything they
+//! started. Whatever a successful hook prints on stdout (trimmed) replaces the
+//! clipboard, e.g. an upload URL.
+
+use cairo::ImageSurface;
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::capture::CaptureInfo;
+use crate::config::Config;
+
+/// Run `hooks.post_capture` on a freshly taken screenshot. `{path}` is a
+/// temporary PNG of the unannotated image, removed when the hook exits.
+pub fn post_capture(config: &Config, info: &CaptureInfo, surface: &ImageSurface) {
+    let command = &config.hooks.post_capture;
+    if command.is_empty() {
+        return;
+    }
+    let path = match write_temp_png(surface) {
+        Ok(path) => path,
+        Err(e) => {
+            log::error!("post_capture hook not run: {}", e);
+            return;
+        }
+    };
+    let vars = variables(config, info, &path, surface.width(), surface.height());
+    spawn("post_capture", command.clone(), config.hooks.timeout, vars, Some(path));
+}
+
+/// Run `hooks.post_save` for a file that was just saved
+pub fn post_save(config: &Config, info: &CaptureInfo, path: &Path, width: i32, height: i32) {
+    let command = &config.hooks.post_save;
+    if command.is_empty() {
+        return;
+    }
+    let vars = variables(config, info, path, width, height);
+    spawn("post_save", command.clone(), config.hooks.timeout, vars, None);
+}
+
+/// Values handed to a hook, by placeholder name
+fn variables(
+    config: &Config,
+    info: &CaptureInfo,
+    path: &Path,
+    width: i32,
+    height: i32,
+) -> Vec<(&'static str, String)> {
+    vec![
+        ("path", path.to_string_lossy().into_owned()),
+        ("mode", info.mode.name().to_string()),
+        ("monitor", info.monitor.map_or("all".to_string(), |m| m.to_string())),
+        ("width", width.to_string()),
+        ("height", height.to_string()),
+        ("time", info.time.to_rfc3339()),
+        (
+            "profile",
+            config.active_profile.clone().unwrap_or_else(|| "default".into()),
+        ),
+    ]
+}
+
+fn spawn(
+    event: &'static str,
+    command: String,
+    timeout: u64,
+    vars: Vec<(&'static str, String)>,
+    temp: Option<PathBuf>,
+) {
+    super::spawn_background(move || {
+        match run(event, &command, Duration::from_secs(timeout), &vars) {
+            Ok(Some(output)) => {
+                if let Err(e) = super::clipboard::copy_text_to_clipboard(&output) {
+                    log::error!("Failed to copy {} hook output: {}", event, e);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => log::error!("{} hook failed: {}", event, e),
+        }
+        if let Some(temp) = temp {
+            let _ = fs::remove_file(temp);
+        }
+    });
+}
+
+/// Run the hook to completion. Returns its trimmed stdout, or None if it printed nothing.
+fn run(
+    event: &str,
+    command: &str,
+    timeout: Duration,
+    vars: &[(&'static str, String)],
+) -> Result<Option<String>, String> {
+    let line = substitute(command, vars);
+    log::debug!("Running {} hook: {}", event, line);
+
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(&line)
+        .envs(vars.iter().map(|(name, value)| {
+            (format!("RAZORSHOT_HOOK_{}", name.to_uppercase()), value)
+        }))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        // Own process group, so a timeout also ends e.g. the curl in `curl | jq`
+        .process_group(0)
+        .spawn()
+        .map_err(|e| format!("failed to start: {e}"))?;
+
+    let mut json = serde_json::Map::new();
+    json.insert("event".into(), event.into());
+    for (name, value) in vars {
+        json.insert((*name).into(), value.as_str().into());
+    }
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that doesn't read stdin may already have closed it
+        let _ = stdin.write_all(serde_json::Value::Object(json).to_string().as_bytes());
+    }
+
+    // Read on another thread so a chatty hook can't fill the pipe and stall
+    let mut stdout = child.stdout.take().ok_or("no stdout")?;
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut output = String::new();
+        let _ = stdout.read_to_string(&mut output);
+        let _ = tx.send(output);
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break status,
+            None if Instant::now() >= deadline => {
+                kill_group(&mut child);
+                return Err(format!("timed out after {}s", timeout.as_secs()));
+            }
+            None => std::thread::sleep(Duration::from_millis(50)),
+        }
+    };
+    if !status.success() {
+        return Err(format!("exited with {status}"));
+    }
+    // Commands left running in the background (`cmd &`, `xdg-open`) keep
+    // stdout open, so the output only gets until the same deadline
+    let output = match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        Ok(output) => output,
+        Err(_) => {
+            kill_group(&mut child);
+            return Err(format!(
+                "timed out after {}s; a background command kept stdout open \
+                 (redirect its output, e.g. `cmd >/dev/null &`)",
+                timeout.as_secs()
+            ));
+        }
+    };
+    let output = output.trim();
+    Ok((!output.is_empty()).then(|| output.to_string()))
+}
+
+/// Kill the hook's shell and everything it started
+fn kill_group(child: &mut Child) {
+    // The shell leads its own group, so the group id is its pid
+    // SAFETY: kill() has no memory-safety requirements
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.wait();
+}
+
+/// Replace `{name}` placeholders with shell-quoted values in one pass over
+/// `command`, so text inside an inserted value is never replaced again.
+/// Unknown placeholders are left as written.
+fn substitute(command: &str, vars: &[(&'static str, String)]) -> String {
+    let mut out = String::with_capacity(command.len());
+    let mut rest = command;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after.find('}').and_then(|end| {
+            let (_, value) = vars.iter().find(|(name, _)| *name == &after[..end])?;
+            Some((end, value))
+        });
+        match value {
+            Some((end, value)) => {
+                out.push_str(&shell_quote(value));
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Quote `value` as a single sh word
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Write the capture where only this user can read it
+fn write_temp_png(surface: &ImageSurface) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let dir = glib::user_runtime_dir().join("razorshot").join("capture");
+    fs::create_dir_all(&dir)?;
+    let name = chrono::Local::now().format("Screenshot_%Y-%m-%d_%H-%M-%S%.3f.png").to_string();
+    let path = dir.join(name);
+    let mut file = fs::File::create(&path)?;
+    surface.write_to_png(&mut file)?;
+    Ok(path)
+}
@@ -0,0 +1,214 @@
+//! Provenance metadata embedded in saved screenshots: PNG text chunks and
+//! JPEG EXIF/XMP. Other formats are written without metadata.
+
+use chrono::{DateTime, Local};
+
+use crate::annotate::shapes::Shape;
+use crate::capture::CaptureInfo;
+use crate::config::MetadataConfig;
+
+/// Largest payload of a JPEG APP1 segment
+const MAX_APP1_PAYLOAD: usize = 65533;
+
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// The fields to embed; None means the field is disabled
+#[derive(Debug, Default)]
+pub struct Metadata {
+    pub capture_time: Option<DateTime<Local>>,
+    pub capture_mode: Option<&'static str>,
+    pub monitor: Option<String>,
+    pub software: Option<String>,
+    /// JSON list of annotation shapes
+    pub annotations: Option<String>,
+}
+
+impl Metadata {
+    pub fn new(config: &MetadataConfig, info: &CaptureInfo, shapes: &[Shape]) -> Self {
+        if config.strip {
+            return Self::default();
+        }
+        let annotations = if config.annotations && !shapes.is_empty() {
+            serde_json::to_string(shapes)
+                .map_err(|e| log::error!("Failed to serialize annotations: {}", e))
+                .ok()
+        } else {
+            None
+        };
+        Self {
+            capture_time: config.capture_time.then_some(info.time),
+            capture_mode: config.capture_mode.then_some(info.mode.name()),
+            monitor: config
+                .monitor
+                .then(|| info.monitor.map_or("all".to_string(), |m| m.to_string())),
+            software: config
+                .software
+                .then(|| format!("razorshot {}", env!("CARGO_PKG_VERSION"))),
+            annotations,
+        }
+    }
+
+    /// PNG text chunks as (keyword, text), using the registered keywords where one fits
+    pub fn png_text(&self) -> Vec<(&'static str, String)> {
+        let mut chunks = Vec::new();
+        if let Some(time) = &self.capture_time {
+            chunks.push(("Creation Time", time.to_rfc3339()));
+        }
+        if let Some(software) = &self.software {
+            chunks.push(("Software", software.clone()));
+        }
+        if let Some(mode) = self.capture_mode {
+            chunks.push(("Capture Mode", mode.to_string()));
+        }
+        if let Some(monitor) = &self.monitor {
+            chunks.push(("Monitor", monitor.clone()));
+        }
+        if let Some(annotations) = &self.annotations {
+            chunks.push(("Annotations", annotations.clone()));
+        }
+        chunks
+    }
+
+    /// EXIF block in TIFF layout (without the `Exif\0\0` prefix), or None if
+    /// no EXIF field is enabled. Annotations only go into XMP.
+    pub fn exif(&self) -> Option<Vec<u8>> {
+        let description = match (self.capture_mode, &self.monitor) {
+            (Some(mode), Some(monitor)) => Some(format!("razorshot {mode} capture, monitor {monitor}")),
+            (Some(mode), None) => Some(format!("razorshot {mode} capture")),
+            (None, Some(monitor)) => Some(format!("razorshot capture, monitor {monitor}")),
+            (None, None) => None,
+        };
+        // Tags must be in ascending order
+        let entries: Vec<(u16, String)> = [
+            (0x010e, description), // ImageDescription
+            (0x0131, self.software.clone()), // Software
+            (0x0132, self.capture_time.map(|t| t.format("%Y:%m:%d %H:%M:%S").to_string())), // DateTime
+        ]
+        .into_iter()
+        .filter_map(|(tag, value)| value.map(|v| (tag, v)))
+        .collect();
+        if entries.is_empty() {
+            return None;
+        }
+        Some(tiff_ascii_ifd(&entries))
+    }
+
+    /// XMP packet for JPEG files, or None if every field is disabled.
+    /// Annotations are left out if they would not fit in a JPEG segment.
+    pub fn xmp(&self) -> Option<String> {
+        if self.capture_time.is_none()
+            && self.capture_mode.is_none()
+            && self.monitor.is_none()
+            && self.software.is_none()
+            && self.annotations.is_none()
+        {
+            return None;
+        }
+        let packet = self.xmp_packet(true);
+        if XMP_HEADER.len() + packet.len() <= MAX_APP1_PAYLOAD {
+            return Some(packet);
+        }
+        log::warn!("Annotations too large for JPEG XMP metadata; leaving them out");
+        Some(self.xmp_packet(false))
+    }
+
+    fn xmp_packet(&self, with_annotations: bool) -> String {
+        let mut props = String::new();
+        if let Some(time) = &self.capture_time {
+            props.push_str(&format!("   <xmp:CreateDate>{}</xmp:CreateDate>\n", time.to_rfc3339()));
+        }
+        if let Some(software) = &self.software {
+            props.push_str(&format!("   <xmp:CreatorTool>{}</xmp:CreatorTool>\n", xml_escape(software)));
+        }
+        if let Some(mode) = self.capture_mode {
+            props.push_str(&format!("   <razorshot:CaptureMode>{mode}</razorshot:CaptureMode>\n"));
+        }
+        if let Some(monitor) = &self.monitor {
+            props.push_str(&format!("   <razorshot:Monitor>{}</razorshot:Monitor>\n", xml_escape(monitor)));
+        }
+        if let Some(annotations) = self.annotations.as_ref().filter(|_| with_annotations) {
+            props.push_str(&format!(
+                "   <razorshot:Annotations>{}</razorshot:Annotations>\n",
+                xml_escape(annotations)
+            ));
+        }
+        format!(
+            "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+             <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n\
+             <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n\
+             <rdf:Description rdf:about=\"\"\n\
+             \x20   xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\"\n\
+             \x20   xmlns:razorshot=\"https://github.com/johnyrahul92/razorshot/ns/1.0/\">\n\
+             {props}\
+             </rdf:Description>\n\
+             </rdf:RDF>\n\
+             </x:xmpmeta>\n\
+             <?xpacket end=\"w\"?>"
+        )
+    }
+}
+
+/// Insert an XMP packet as an APP1 segment after the leading APPn segments
+/// (JFIF, EXIF) of an encoded JPEG.
+pub fn insert_jpeg_xmp(jpeg: &mut Vec<u8>, xmp: &str) -> Result<(), String> {
+    let mut payload = XMP_HEADER.to_vec();
+    payload.extend_from_slice(xmp.as_bytes());
+    if payload.len() > MAX_APP1_PAYLOAD {
+        return Err("XMP packet too large for a JPEG segment".into());
+    }
+    if !jpeg.starts_with(&[0xff, 0xd8]) {
+        return Err("not a JPEG stream".into());
+    }
+
+    // Skip SOI and any APP0-APP15 segments
+    let mut pos = 2;
+    while pos + 4 <= jpeg.len() && jpeg[pos] == 0xff && (0xe0..=0xef).contains(&jpeg[pos + 1]) {
+        let len = u16::from_be_bytes([jpeg[pos + 2], jpeg[pos + 3]]) as usize;
+        pos += 2 + len;
+    }
+    let pos = pos.min(jpeg.len());
+
+    let mut segment = vec![0xff, 0xe1];
+    segment.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+    segment.extend_from_slice(&payload);
+    jpeg.splice(pos..pos, segment);
+    Ok(())
+}
+
+/// A little-endian TIFF header and one IFD of ASCII entries
+fn tiff_ascii_ifd(entries: &[(u16, String)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(b"II");
+    out.extend_from_slice(&42u16.to_le_bytes());
+    out.extend_from_slice(&8u32.to_le_bytes());
+
+    // Values longer than four bytes live after the IFD
+    let mut data_offset = 8 + 2 + entries.len() * 12 + 4;
+    let mut data = Vec::new();
+    out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    for (tag, value) in entries {
+        let mut bytes: Vec<u8> = value.chars().map(|c| if c.is_ascii() { c as u8 } else { b'?' }).collect();
+        bytes.push(0);
+        out.extend_from_slice(&tag.to_le_bytes());
+        out.extend_from_slice(&2u16.to_le_bytes()); // ASCII
+        out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        if bytes.len() <= 4 {
+            bytes.resize(4, 0);
+            out.extend_from_slice(&bytes);
+        } else {
+            out.extend_from_slice(&(data_offset as u32).to_le_bytes());
+            data_offset += bytes.len();
+            data.extend_from_slice(&bytes);
+        }
+    }
+    out.extend_from_slice(&0u32.to_le_bytes()); // no next IFD
+    out.extend_from_slice(&data);
+    out
+}
+
+pub(super) fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
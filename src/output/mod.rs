@@ -1,2 +1,43 @@
 pub mod clipboard;
+pub mod drag;
 pub mod file;
+pub mod filename;
+pub mod hooks;
+pub mod metadata;
+pub mod png_encoder;
+pub mod s3;
+pub mod upload;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Hooks and uploads started and not yet finished
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// Run `job` on a background thread, counted by `when_idle`
+fn spawn_background<F: FnOnce() + Send + 'static>(job: F) {
+    RUNNING.fetch_add(1, Ordering::SeqCst);
+    std::thread::spawn(move || {
+        job();
+        RUNNING.fetch_sub(1, Ordering::SeqCst);
+    });
+}
+
+/// Call `f` once every running hook and upload has finished, so exiting
+/// doesn't cut off their clipboard updates
+pub fn when_idle<F: FnOnce() + 'static>(f: F) {
+    if RUNNING.load(Ordering::SeqCst) == 0 {
+        f();
+        return;
+    }
+    let mut f = Some(f);
+    glib::timeout_add_local(Duration::from_millis(100), move || {
+        if RUNNING.load(Ordering::SeqCst) > 0 {
+            return glib::ControlFlow::Continue;
+        }
+        if let Some(f) = f.take() {
+            f();
+        }
+        glib::ControlFlow::Break
+    });
+}
@@ -0,0 +1,102 @@
+//! PNG encoding with the size options from `[export]`: compression level,
+//! row filter, lossless palette reduction and alpha stripping. Also writes
+//! the metadata text chunks.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::config::{ExportConfig, PngFilter};
+
+/// Encode straight RGBA pixels as a PNG file with `text` as (keyword, text) chunks
+pub fn write_png(
+    path: &Path,
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    export: &ExportConfig,
+    text: &[(&str, String)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let file = fs::File::create(path)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_deflate_compression(match export.png_compression {
+        0 => png::DeflateCompression::NoCompression,
+        level => png::DeflateCompression::Level(level),
+    });
+    encoder.set_filter(match export.png_filter {
+        PngFilter::None => png::Filter::NoFilter,
+        PngFilter::Sub => png::Filter::Sub,
+        PngFilter::Up => png::Filter::Up,
+        PngFilter::Avg => png::Filter::Avg,
+        PngFilter::Paeth => png::Filter::Paeth,
+        PngFilter::Adaptive => png::Filter::Adaptive,
+    });
+
+    let opaque = rgba.chunks_exact(4).all(|p| p[3] == 255);
+    let indexed = if export.png_palette {
+        palettize(rgba)
+    } else {
+        None
+    };
+
+    let data = if let Some((palette, indices)) = indexed {
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_palette(palette.iter().flat_map(|c| [c[0], c[1], c[2]]).collect::<Vec<_>>());
+        // Translucent entries are sorted first, so tRNS can stop at the last one
+        let trns: Vec<u8> = palette.iter().map(|c| c[3]).take_while(|&a| a != 255).collect();
+        if !trns.is_empty() {
+            encoder.set_trns(trns);
+        }
+        indices
+    } else if export.png_strip_alpha && opaque {
+        encoder.set_color(png::ColorType::Rgb);
+        rgba.chunks_exact(4).flat_map(|p| [p[0], p[1], p[2]]).collect()
+    } else {
+        encoder.set_color(png::ColorType::Rgba);
+        rgba.to_vec()
+    };
+
+    for (keyword, value) in text {
+        // tEXt is Latin-1 only; anything else needs the UTF-8 iTXt chunk
+        if value.chars().all(|c| (c as u32) < 0x100) {
+            encoder.add_text_chunk(keyword.to_string(), value.clone())?;
+        } else {
+            encoder.add_itxt_chunk(keyword.to_string(), value.clone())?;
+        }
+    }
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Build a palette and per-pixel indices if the image has at most 256
+/// distinct colors. Translucent colors come first in the palette.
+fn palettize(rgba: &[u8]) -> Option<(Vec<[u8; 4]>, Vec<u8>)> {
+    let mut palette: Vec<[u8; 4]> = Vec::new();
+    let mut seen: HashSet<[u8; 4]> = HashSet::new();
+    for p in rgba.chunks_exact(4) {
+        let color = [p[0], p[1], p[2], p[3]];
+        if seen.insert(color) {
+            if palette.len() == 256 {
+                return None;
+            }
+            palette.push(color);
+        }
+    }
+    palette.sort_by_key(|c| c[3] == 255);
+
+    let index: HashMap<[u8; 4], u8> = palette
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (*c, i as u8))
+        .collect();
+    let indices = rgba
+        .chunks_exact(4)
+        .map(|p| index[&[p[0], p[1], p[2], p[3]]])
+        .collect();
+    Some((palette, indices))
+}
@@ -0,0 +1,391 @@
+//! `[upload]`: sending saved screenshots to the configured `target` and
+//! putting the returned link on the clipboard. `s3` is handled by
+//! `output::s3`; `http` is a generic endpoint, below.
+//!
+//! `post` sends a `multipart/form-data` body with the file in `field` plus
+//! any extra `form` fields; `put` sends the file as the raw body. The link is
+//! read from the JSON response at `response_url` (dotted keys, numbers index
+//! arrays), or is the whole trimmed response body when that is empty.
+
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::capture::CaptureInfo;
+use crate::config::{Config, ExportFormat, HttpMethod, HttpUploadConfig, UploadTarget};
+use crate::output::filename::TemplateValues;
+
+/// Upload a file that was just saved if `upload.auto` is on, copying the link
+/// when done
+pub fn upload_saved(config: &Config, info: &CaptureInfo, path: &Path, width: i32, height: i32) {
+    if config.upload.auto {
+        let values = TemplateValues::new(config, info, width, height);
+        upload_in_background(config, values, path, |_| {});
+    }
+}
+
+/// Upload `path` on a background thread and copy the link to the clipboard.
+/// `values` fill in the S3 key prefix. `on_done` gets the link or the error
+/// on the main thread.
+pub fn upload_in_background<F>(config: &Config, values: TemplateValues, path: &Path, on_done: F)
+where
+    F: FnOnce(Result<String, String>) + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    let config = config.clone();
+    let path = path.to_path_buf();
+    super::spawn_background(move || {
+        let result = upload_file(&config, &path, &values);
+        match &result {
+            Ok(url) => {
+                log::info!("Uploaded {} to {}", path.display(), url);
+                crate::history::set_upload_url(&path, url);
+                if let Err(e) = super::clipboard::copy_text_to_clipboard(url) {
+                    log::error!("Failed to copy upload URL: {}", e);
+                }
+            }
+            Err(e) => log::error!("Upload of {} failed: {}", path.display(), e),
+        }
+        let _ = tx.send(result);
+    });
+
+    let mut on_done = Some(on_done);
+    glib::timeout_add_local(Duration::from_millis(100), move || match rx.try_recv() {
+        Ok(result) => {
+            if let Some(on_done) = on_done.take() {
+                on_done(result);
+            }
+            glib::ControlFlow::Break
+        }
+        Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
+        Err(mpsc::TryRecvError::Disconnected) => glib::ControlFlow::Break,
+    });
+}
+
+/// Upload `path` to `upload.target` and return its link. Blocks until the
+/// server answers.
+pub fn upload_file(
+    config: &Config,
+    path: &Path,
+    values: &TemplateValues,
+) -> Result<String, String> {
+    match config.upload.target {
+        UploadTarget::Http => upload_http(&config.upload.http, path),
+        UploadTarget::S3 => super::s3::upload(&config.upload.s3, path, values),
+    }
+}
+
+/// MIME type for a saved file, from its extension
+pub(super) fn content_type(path: &Path) -> &'static str {
+    path.extension()
+        .and_then(|ext| ExportFormat::from_extension(&ext.to_string_lossy()))
+        .map_or("application/octet-stream", |format| format.mime_type())
+}
+
+fn upload_http(http: &HttpUploadConfig, path: &Path) -> Result<String, String> {
+    if http.url.is_empty() {
+        return Err("No upload endpoint configured; set upload.http.url".into());
+    }
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let filename = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "screenshot".into());
+    let mime = content_type(path);
+
+    let url = http.url.replace("{filename}", &percent_encode(&filename));
+    let agent = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(http.timeout))
+        .build();
+    let (method, body_type, body) = match http.method {
+        HttpMethod::Post => {
+            let boundary = boundary();
+            let body = multipart_body(http, &boundary, &filename, mime, &data);
+            ("POST", format!("multipart/form-data; boundary={boundary}"), body)
+        }
+        HttpMethod::Put => ("PUT", mime.to_string(), data),
+    };
+    let mut request = agent.request(method, &url).set("Content-Type", &body_type);
+    for (name, value) in &http.headers {
+        request = request.set(name, value);
+    }
+
+    let response = match request.send_bytes(&body) {
+        Ok(response) => response,
+        Err(ureq::Error::Status(code, response)) => {
+            let text = response.into_string().unwrap_or_default();
+            return Err(format!("Server answered {code}: {}", snippet(&text)));
+        }
+        Err(e) => return Err(format!("Upload failed: {e}")),
+    };
+    let text = response
+        .into_string()
+        .map_err(|e| format!("Failed to read the response: {e}"))?;
+    link_from_response(&text, &http.response_url)
+}
+
+/// Build a `multipart/form-data` body: the extra form fields, then the file
+fn multipart_body(
+    http: &HttpUploadConfig,
+    boundary: &str,
+    filename: &str,
+    mime: &str,
+    data: &[u8],
+) -> Vec<u8> {
+    let mut body = Vec::with_capacity(data.len() + 512);
+    for (name, value) in &http.form {
+        body.extend_from_slice(
+            format!(
+                "--{boundary}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{value}\r\n",
+                quote(name)
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n\
+             Content-Type: {mime}\r\n\r\n",
+            quote(&http.field),
+            quote(filename)
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(data);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+    body
+}
+
+/// Pick the link out of the server's answer
+fn link_from_response(text: &str, response_url: &str) -> Result<String, String> {
+    if response_url.is_empty() {
+        let link = text.trim();
+        if link.is_empty() {
+            return Err("The server's response was empty".into());
+        }
+        return Ok(link.to_string());
+    }
+
+    let json: serde_json::Value = serde_json::from_str(text)
+        .map_err(|e| format!("Response is not JSON ({e}): {}", snippet(text)))?;
+    let mut value = &json;
+    for key in response_url.split('.') {
+        let next = match value {
+            serde_json::Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => value.get(key),
+        };
+        value = next
+            .ok_or_else(|| format!("No \"{response_url}\" in response: {}", snippet(text)))?;
+    }
+    match value {
+        serde_json::Value::String(link) if !link.is_empty() => Ok(link.clone()),
+        _ => Err(format!("\"{response_url}\" in response is not a link: {value}")),
+    }
+}
+
+/// A multipart boundary that won't turn up in PNG data by accident
+fn boundary() -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    format!("razorshot-{:x}-{:x}", std::process::id(), nanos)
+}
+
+/// Escape a form field name or file name for a quoted header parameter
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Percent-encode everything but URL-safe characters
+pub(super) fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// The start of a response, for error messages
+fn snippet(text: &str) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(200) {
+        Some((i, _)) => format!("{}…", &text[..i]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+    use std::thread::{self, JoinHandle};
+
+    pub(crate) const PNG: &[u8] = b"\x89PNG\r\n\x1a\nnot really an image";
+
+    /// A request as the stand-in server received it
+    pub(crate) struct Request {
+        pub line: String,
+        /// Names lowercased
+        pub headers: Vec<(String, String)>,
+        pub body: Vec<u8>,
+    }
+
+    impl Request {
+        pub fn header(&self, name: &str) -> Option<&str> {
+            self.headers
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, value)| value.as_str())
+        }
+    }
+
+    /// A local HTTP server that takes one request and answers 200 with
+    /// `response`. Returns its base URL and the request it received.
+    pub(crate) fn stand_in(response: &'static str) -> (String, JoinHandle<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let mut headers = Vec::new();
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                let Some((name, value)) = header.trim_end().split_once(':') else {
+                    break;
+                };
+                headers.push((name.to_ascii_lowercase(), value.trim().to_string()));
+            }
+            let length = headers
+                .iter()
+                .find(|(name, _)| name == "content-length")
+                .map_or(0, |(_, value)| value.parse().unwrap());
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+
+            let mut stream = reader.into_inner();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                response.len()
+            )
+            .unwrap();
+            Request { line: line.trim_end().to_string(), headers, body }
+        });
+        (base, server)
+    }
+
+    /// A file holding `PNG`, in a directory of its own per test run
+    pub(crate) fn temp_file(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("razorshot-upload-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, PNG).unwrap();
+        path
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|window| window == needle)
+    }
+
+    #[test]
+    fn post_sends_multipart_form() {
+        let (base, server) = stand_in(r#"{"data": {"link": "https://img.example.com/abc.png"}}"#);
+        let path = temp_file("post.png");
+        let http = HttpUploadConfig {
+            url: format!("{base}/api/upload"),
+            method: HttpMethod::Post,
+            field: "image".into(),
+            form: BTreeMap::from([("expire".into(), "7d".into())]),
+            headers: BTreeMap::from([("Authorization".into(), "Bearer secret".into())]),
+            response_url: "data.link".into(),
+            ..Default::default()
+        };
+
+        let link = upload_http(&http, &path);
+        let request = server.join().unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(link.as_deref(), Ok("https://img.example.com/abc.png"));
+        assert_eq!(request.line, "POST /api/upload HTTP/1.1");
+        assert_eq!(request.header("authorization"), Some("Bearer secret"));
+
+        let content_type = request.header("content-type").unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/form-data; boundary=")
+            .expect(content_type);
+        let form_part = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"expire\"\r\n\r\n7d\r\n"
+        );
+        let file_part = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"image\"; \
+             filename=\"post.png\"\r\nContent-Type: image/png\r\n\r\n"
+        );
+        assert!(request.body.starts_with(form_part.as_bytes()));
+        assert!(contains(&request.body, file_part.as_bytes()));
+        assert!(contains(&request.body, PNG));
+        assert!(request.body.ends_with(format!("\r\n--{boundary}--\r\n").as_bytes()));
+    }
+
+    #[test]
+    fn put_sends_raw_body() {
+        let (base, server) = stand_in("https://files.example.com/put%20me.png\n");
+        let path = temp_file("put me.png");
+        let http = HttpUploadConfig {
+            url: format!("{base}/dav/{{filename}}"),
+            method: HttpMethod::Put,
+            headers: BTreeMap::from([("X-Api-Key".into(), "k3y".into())]),
+            ..Default::default()
+        };
+
+        let link = upload_http(&http, &path);
+        let request = server.join().unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(link.as_deref(), Ok("https://files.example.com/put%20me.png"));
+        assert_eq!(request.line, "PUT /dav/put%20me.png HTTP/1.1");
+        assert_eq!(request.header("content-type"), Some("image/png"));
+        assert_eq!(request.header("x-api-key"), Some("k3y"));
+        assert_eq!(request.body, PNG);
+    }
+
+    #[test]
+    fn link_from_whole_body() {
+        assert_eq!(
+            link_from_response("  https://x.example/a.png\n", "").as_deref(),
+            Ok("https://x.example/a.png")
+        );
+        assert!(link_from_response(" \n", "").is_err());
+    }
+
+    #[test]
+    fn link_from_json_path() {
+        let text = r#"{"data": {"link": "https://x.example/a.png"}}"#;
+        assert_eq!(link_from_response(text, "data.link").as_deref(), Ok("https://x.example/a.png"));
+
+        let text = r#"{"files": [{"url": "https://x.example/0.png"}, {"url": "https://x.example/1.png"}]}"#;
+        assert_eq!(link_from_response(text, "files.0.url").as_deref(), Ok("https://x.example/0.png"));
+        assert_eq!(link_from_response(text, "files.1.url").as_deref(), Ok("https://x.example/1.png"));
+    }
+
+    #[test]
+    fn link_errors() {
+        let text = r#"{"data": {"link": 42, "files": []}}"#;
+        let missing = link_from_response(text, "data.url").unwrap_err();
+        assert!(missing.starts_with("No \"data.url\" in response"), "{missing}");
+        let index = link_from_response(text, "data.files.0").unwrap_err();
+        assert!(index.starts_with("No \"data.files.0\" in response"), "{index}");
+        let number = link_from_response(text, "data.link").unwrap_err();
+        assert!(number.contains("is not a link: 42"), "{number}");
+        let html = link_from_response("<html>Bad Gateway</html>", "data.link").unwrap_err();
+        assert!(html.starts_with("Response is not JSON"), "{html}");
+    }
+}
@@ -1,4 +1,8 @@
-use ksni::{self, menu::StandardItem, Icon, Tray, TrayMethods};
+use ksni::{
+    self,
+    menu::{CheckmarkItem, RadioGroup, RadioItem, StandardItem, SubMenu},
+    Icon, Tray, TrayMethods,
+};
 use std::sync::mpsc;
 
 /// Messages from the tray to the GTK main thread
@@ -6,11 +10,34 @@ use std::sync::mpsc;
 pub enum TrayAction {
     CaptureRegion,
     CaptureFullScreen,
+    /// Open the history gallery
+    ShowHistory,
+    /// Switch to a named profile, or back to the base config with None
+    SelectProfile(Option<String>),
+    /// Turn uploading of saved screenshots on or off
+    SetUpload(bool),
     Quit,
 }
 
+/// Messages from the GTK main thread to the tray
+#[derive(Debug, Clone)]
+pub enum TrayUpdate {
+    /// The config was reloaded; rebuild the profile menu
+    Profiles {
+        profiles: Vec<String>,
+        active: Option<String>,
+    },
+    /// The config changed whether screenshots are uploaded
+    Upload { enabled: bool },
+}
+
 struct RazorshotTray {
     tx: mpsc::SyncSender<TrayAction>,
+    profiles: Vec<String>,
+    /// Index into the profile menu; 0 is the base config
+    selected_profile: usize,
+    /// Mirrors `upload.auto`
+    upload: bool,
 }
 
 /// Generate a 48x48 ARGB32 camera icon (network byte order: A R G B per pixel)
@@ -26,9 +53,9 @@ fn generate_icon() -> Icon {
             let fy = y as f32;
 
             // Camera body: rect (5,14) to (43,42) with rounded feel
-            let in_body = x >= 5 && x <= 42 && y >= 14 && y <= 41;
+            let in_body = (5..=42).contains(&x) && (14..=41).contains(&y);
             // Top bar (viewfinder): rect (14,8) to (34,15)
-            let in_top = x >= 14 && x <= 33 && y >= 8 && y <= 14;
+            let in_top = (14..=33).contains(&x) && (8..=14).contains(&y);
             // Lens: circle at center (24, 29), radius 9
             let dx = fx - 24.0;
             let dy = fy - 29.0;
@@ -89,8 +116,9 @@ impl Tray for RazorshotTray {
     fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
         let tx_region = self.tx.clone();
         let tx_full = self.tx.clone();
+        let tx_history = self.tx.clone();
         let tx_quit = self.tx.clone();
-        vec![
+        let mut items = vec![
             ksni::MenuItem::Standard(StandardItem {
                 label: "Capture Region".into(),
                 activate: Box::new(move |_| {
@@ -107,7 +135,61 @@ impl Tray for RazorshotTray {
                 }),
                 ..Default::default()
             }),
+            ksni::MenuItem::Standard(StandardItem {
+                label: "History…".into(),
+                activate: Box::new(move |_| {
+                    log::info!("Tray: History clicked");
+                    let _ = tx_history.try_send(TrayAction::ShowHistory);
+                }),
+                ..Default::default()
+            }),
+        ];
+
+        if !self.profiles.is_empty() {
+            let mut options = vec![RadioItem {
+                label: "Default".into(),
+                ..Default::default()
+            }];
+            options.extend(self.profiles.iter().map(|name| RadioItem {
+                // Underscores mark access keys in menu labels
+                label: name.replace('_', "__"),
+                ..Default::default()
+            }));
+            items.push(ksni::MenuItem::Separator);
+            items.push(
+                SubMenu {
+                    label: "Profile".into(),
+                    submenu: vec![RadioGroup {
+                        selected: self.selected_profile,
+                        select: Box::new(|tray: &mut Self, index| {
+                            tray.selected_profile = index;
+                            let profile = index
+                                .checked_sub(1)
+                                .and_then(|i| tray.profiles.get(i).cloned());
+                            log::info!("Tray: profile {:?} selected", profile);
+                            let _ = tray.tx.try_send(TrayAction::SelectProfile(profile));
+                        }),
+                        options,
+                    }
+                    .into()],
+                    ..Default::default()
+                }
+                .into(),
+            );
+        }
+
+        items.extend([
             ksni::MenuItem::Separator,
+            ksni::MenuItem::Checkmark(CheckmarkItem {
+                label: "Upload Screenshots".into(),
+                checked: self.upload,
+                activate: Box::new(|tray: &mut Self| {
+                    tray.upload = !tray.upload;
+                    log::info!("Tray: upload {}", if tray.upload { "on" } else { "off" });
+                    let _ = tray.tx.try_send(TrayAction::SetUpload(tray.upload));
+                }),
+                ..Default::default()
+            }),
             ksni::MenuItem::Standard(StandardItem {
                 label: "Quit".into(),
                 activate: Box::new(move |_| {
@@ -116,19 +198,41 @@ impl Tray for RazorshotTray {
                 }),
                 ..Default::default()
             }),
-        ]
+        ]);
+        items
     }
 }
 
-/// Start the system tray in a background thread.
-/// Returns a receiver that the GTK main loop should poll for TrayAction messages.
-pub fn start_tray() -> mpsc::Receiver<TrayAction> {
+/// Menu index of the `active` profile; 0 is the base config
+fn profile_index(profiles: &[String], active: Option<&str>) -> usize {
+    active
+        .and_then(|name| profiles.iter().position(|p| p == name))
+        .map_or(0, |i| i + 1)
+}
+
+/// Start the system tray in a background thread, listing `profiles` in its menu
+/// with `active` preselected and the upload toggle set to `upload`.
+/// Returns a receiver that the GTK main loop should poll for TrayAction messages,
+/// and a sender for changing the menu afterwards.
+pub fn start_tray(
+    profiles: Vec<String>,
+    active: Option<String>,
+    upload: bool,
+) -> (mpsc::Receiver<TrayAction>, mpsc::Sender<TrayUpdate>) {
     let (tx, rx) = mpsc::sync_channel(16);
+    let (update_tx, update_rx) = mpsc::channel::<TrayUpdate>();
+    let selected_profile = profile_index(&profiles, active.as_deref());
 
     std::thread::spawn(move || {
         log::info!("Starting system tray service...");
         let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime for tray");
-        match rt.block_on(RazorshotTray { tx }.spawn()) {
+        let tray = RazorshotTray {
+            tx,
+            profiles,
+            selected_profile,
+            upload,
+        };
+        match rt.block_on(tray.spawn()) {
             Ok(handle) => {
                 log::info!("Tray service started");
                 // Keep the thread alive until the handle is closed
@@ -137,6 +241,22 @@ pub fn start_tray() -> mpsc::Receiver<TrayAction> {
                         if handle.is_closed() {
                             break;
                         }
+                        while let Ok(update) = update_rx.try_recv() {
+                            match update {
+                                TrayUpdate::Profiles { profiles, active } => {
+                                    handle
+                                        .update(|tray| {
+                                            tray.selected_profile =
+                                                profile_index(&profiles, active.as_deref());
+                                            tray.profiles = profiles;
+                                        })
+                                        .await;
+                                }
+                                TrayUpdate::Upload { enabled } => {
+                                    handle.update(|tray| tray.upload = enabled).await;
+                                }
+                            }
+                        }
                         tokio::time::sleep(std::time::Duration::from_millis(100)).await;
                     }
                 });
@@ -145,5 +265,5 @@ pub fn start_tray() -> mpsc::Receiver<TrayAction> {
         }
     });
 
-    rx
+    (rx, update_tx)
 }
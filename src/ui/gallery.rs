@@ -0,0 +1,252 @@
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+use gtk4::prelude::*;
+use gtk4::{
+    ApplicationWindow, Box as GtkBox, Button, FlowBox, FlowBoxChild, Label, Orientation,
+    ScrolledWindow, SearchEntry,
+};
+
+use crate::capture::{self, CaptureInfo};
+use crate::config::Config;
+use crate::history::{self, Entry};
+use crate::output;
+use crate::ui::feedback::show_error;
+
+/// Size of the thumbnail area on each card
+const CARD_WIDTH: i32 = 220;
+const CARD_HEIGHT: i32 = 140;
+
+/// The gallery's widgets, shared by the card buttons
+#[derive(Clone)]
+struct Gallery {
+    app: gtk4::Application,
+    window: ApplicationWindow,
+    config: Rc<Config>,
+    flowbox: FlowBox,
+    search: SearchEntry,
+    /// Shown instead of the cards when none match
+    empty: Label,
+    cards: Rc<RefCell<Vec<(FlowBoxChild, Entry)>>>,
+}
+
+impl Gallery {
+    /// Show the cards whose capture date matches the search text
+    fn filter(&self) {
+        let query = self.search.text();
+        let mut shown = 0;
+        for (child, entry) in self.cards.borrow().iter() {
+            let visible = entry.matches_date(&query);
+            child.set_visible(visible);
+            shown += visible as usize;
+        }
+        self.empty.set_visible(shown == 0);
+    }
+
+    fn show_error(&self, title: &str, detail: &str) {
+        show_error(Some(&self.window.clone().upcast()), title, detail);
+    }
+}
+
+/// Open the gallery of saved screenshots, newest first. Screenshots whose
+/// file is gone are left out.
+pub fn show_gallery(app: &gtk4::Application, config: Config) {
+    let window = ApplicationWindow::builder()
+        .application(app)
+        .title("Razorshot - History")
+        .default_width(960)
+        .default_height(640)
+        .build();
+
+    let main_box = GtkBox::new(Orientation::Vertical, 0);
+
+    let search = SearchEntry::new();
+    search.set_placeholder_text(Some("Filter by date, e.g. 2025-01 or 2025-01-31"));
+    search.set_margin_top(8);
+    search.set_margin_bottom(8);
+    search.set_margin_start(8);
+    search.set_margin_end(8);
+    main_box.append(&search);
+
+    let flowbox = FlowBox::new();
+    flowbox.set_selection_mode(gtk4::SelectionMode::None);
+    flowbox.set_homogeneous(true);
+    flowbox.set_valign(gtk4::Align::Start);
+    flowbox.set_max_children_per_line(8);
+    flowbox.set_row_spacing(8);
+    flowbox.set_column_spacing(8);
+    flowbox.set_margin_start(8);
+    flowbox.set_margin_end(8);
+    flowbox.set_margin_bottom(8);
+
+    let scrolled = ScrolledWindow::new();
+    scrolled.set_vexpand(true);
+    scrolled.set_child(Some(&flowbox));
+    main_box.append(&scrolled);
+
+    let empty = Label::new(Some(if config.history.enabled {
+        "No screenshots found"
+    } else {
+        "The history is turned off (history.enabled)"
+    }));
+    empty.add_css_class("dim-label");
+    empty.set_vexpand(true);
+    main_box.append(&empty);
+
+    let gallery = Gallery {
+        app: app.clone(),
+        window: window.clone(),
+        config: Rc::new(config),
+        flowbox,
+        search: search.clone(),
+        empty,
+        cards: Rc::new(RefCell::new(Vec::new())),
+    };
+    for entry in history::load().into_iter().rev() {
+        if !entry.path.exists() {
+            continue;
+        }
+        let child = FlowBoxChild::new();
+        child.set_child(Some(&card(&gallery, &entry, &child)));
+        gallery.flowbox.insert(&child, -1);
+        gallery.cards.borrow_mut().push((child, entry));
+    }
+    gallery.filter();
+
+    let gallery_search = gallery.clone();
+    search.connect_search_changed(move |_| gallery_search.filter());
+
+    let key_ctrl = gtk4::EventControllerKey::new();
+    let window_key = window.clone();
+    key_ctrl.connect_key_pressed(move |_, keyval, _, _| {
+        if keyval == gdk4::Key::Escape {
+            window_key.close();
+            return glib::Propagation::Stop;
+        }
+        glib::Propagation::Proceed
+    });
+    window.add_controller(key_ctrl);
+
+    window.set_child(Some(&main_box));
+    window.present();
+}
+
+/// A thumbnail with the capture's details and its actions
+fn card(gallery: &Gallery, entry: &Entry, child: &FlowBoxChild) -> GtkBox {
+    let card = GtkBox::new(Orientation::Vertical, 4);
+    card.set_tooltip_text(Some(&entry.path.to_string_lossy()));
+
+    let picture = gtk4::Picture::for_filename(entry.thumbnail.as_deref().unwrap_or(&entry.path));
+    picture.set_content_fit(gtk4::ContentFit::Contain);
+    picture.set_can_shrink(true);
+    picture.set_size_request(CARD_WIDTH, CARD_HEIGHT);
+    card.append(&picture);
+
+    let time = Label::new(Some(&entry.local_time()));
+    time.set_xalign(0.0);
+    card.append(&time);
+    let details = Label::new(Some(&format!("{}×{} · {}", entry.width, entry.height, entry.mode)));
+    details.set_xalign(0.0);
+    details.add_css_class("dim-label");
+    card.append(&details);
+
+    let buttons = GtkBox::new(Orientation::Horizontal, 4);
+    let edit_btn = Button::with_label("Edit");
+    edit_btn.set_tooltip_text(Some("Open in the annotation editor"));
+    let copy_btn = Button::with_label("Copy");
+    copy_btn.set_tooltip_text(Some("Copy to the clipboard again"));
+    let delete_btn = Button::with_label("Delete");
+    delete_btn.set_tooltip_text(Some("Delete the file"));
+    delete_btn.add_css_class("destructive-action");
+    buttons.append(&edit_btn);
+    buttons.append(&copy_btn);
+    if let Some(url) = entry.upload_url.clone() {
+        let link_btn = Button::with_label("Link");
+        link_btn.set_tooltip_text(Some(&format!("Copy {url}")));
+        let gallery_link = gallery.clone();
+        link_btn.connect_clicked(move |_| {
+            if let Err(e) = output::clipboard::copy_text_to_clipboard(&url) {
+                gallery_link.show_error("Clipboard Failed", &e.to_string());
+            }
+        });
+        buttons.append(&link_btn);
+    }
+    buttons.append(&delete_btn);
+    card.append(&buttons);
+
+    // Edits are saved as a new screenshot; the original stays as it is
+    let gallery_edit = gallery.clone();
+    let path_edit = entry.path.clone();
+    edit_btn.connect_clicked(move |_| match capture::file::load_image(&path_edit) {
+        Ok(surface) => crate::ui::window::show_editor(
+            &gallery_edit.app,
+            surface,
+            (*gallery_edit.config).clone(),
+            CaptureInfo::file(),
+        ),
+        Err(e) => gallery_edit.show_error("Open Failed", &e),
+    });
+
+    let gallery_copy = gallery.clone();
+    let path_copy = entry.path.clone();
+    copy_btn.connect_clicked(move |_| {
+        if let Err(e) = copy_again(&path_copy, &gallery_copy.config) {
+            gallery_copy.show_error("Clipboard Failed", &e);
+        }
+    });
+
+    let gallery_delete = gallery.clone();
+    let path_delete = entry.path.clone();
+    let child = child.clone();
+    delete_btn.connect_clicked(move |_| {
+        confirm_delete(&gallery_delete, &path_delete, &child);
+    });
+
+    card
+}
+
+/// Ask before deleting the file, then drop it from the history and the gallery
+fn confirm_delete(gallery: &Gallery, path: &Path, child: &FlowBoxChild) {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let dialog = gtk4::AlertDialog::builder()
+        .modal(true)
+        .message(format!("Delete {name}?"))
+        .detail("The file is removed from disk.")
+        .buttons(["Cancel", "Delete"])
+        .cancel_button(0)
+        .default_button(0)
+        .build();
+
+    let window = gallery.window.clone();
+    let gallery = gallery.clone();
+    let path = path.to_path_buf();
+    let child = child.clone();
+    dialog.choose(Some(&window), gio::Cancellable::NONE, move |result| {
+        // 1 is Delete; Cancel and Escape give 0 or an error
+        if !matches!(result, Ok(1)) {
+            return;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                gallery.show_error("Delete Failed", &e.to_string());
+                return;
+            }
+        }
+        log::info!("Deleted {}", path.display());
+        history::remove(&path);
+        gallery.flowbox.remove(&child);
+        gallery.cards.borrow_mut().retain(|(c, _)| *c != child);
+        gallery.filter();
+    });
+}
+
+/// Put a saved screenshot on the clipboard again, in the configured mode
+fn copy_again(path: &Path, config: &Config) -> Result<(), String> {
+    let surface = capture::file::load_image(path)?;
+    output::clipboard::copy_to_clipboard(&surface, Some(path), config.behavior.clipboard_mode)
+        .map_err(|e| e.to_string())
+}
@@ -1,3 +1,4 @@
 pub mod feedback;
+pub mod gallery;
 pub mod selection_overlay;
 pub mod window;
@@ -12,12 +12,15 @@ struct SelectionState {
     _cancelled: bool,
 }
 
+/// Called with the selected region as (x, y, width, height), or None if cancelled
+pub type SelectionCallback = Box<dyn FnOnce(Option<(i32, i32, i32, i32)>) + 'static>;
+
 /// Show a fullscreen overlay for region selection on top of the captured screenshot.
 /// Returns the selected region as (x, y, width, height), or None if cancelled.
 pub fn show_selection_overlay(
     app: &gtk4::Application,
     screenshot: &ImageSurface,
-    callback: Box<dyn FnOnce(Option<(i32, i32, i32, i32)>) + 'static>,
+    callback: SelectionCallback,
 ) {
     let window = ApplicationWindow::builder()
         .application(app)
@@ -1,5 +1,6 @@
 use cairo::ImageSurface;
 use std::cell::RefCell;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 use gtk4::prelude::*;
@@ -7,19 +8,24 @@ use gtk4::{
     ApplicationWindow, Box as GtkBox, Button, Entry, Orientation, Popover, ScrolledWindow,
 };
 
-use crate::annotate::canvas::{build_canvas, render_final_image};
+use crate::annotate::canvas::{build_canvas, render_final_image, CanvasState};
 use crate::annotate::toolbar::build_toolbar;
 use crate::annotate::tools::ToolKind;
-use crate::config::Config;
+use crate::capture::{self, CaptureInfo};
+use crate::config::{Config, DoneAction, ExportFormat};
 use crate::output;
 
 /// Open the annotation editor window with the given screenshot.
+/// `info` describes the capture for the filename template.
 pub fn show_editor(
     app: &gtk4::Application,
     surface: ImageSurface,
     config: Config,
+    info: CaptureInfo,
 ) {
     let (drawing_area, state) = build_canvas(surface, &config);
+    // The file written by the last Save or Save As, for Copy's path/URI modes
+    let last_saved: Rc<RefCell<Option<PathBuf>>> = Rc::new(RefCell::new(None));
 
     let window = ApplicationWindow::builder()
         .application(app)
@@ -41,10 +47,26 @@ pub fn show_editor(
     action_box.set_halign(gtk4::Align::End);
     action_box.set_hexpand(true);
 
+    let copy_btn = Button::with_label("Copy");
+    copy_btn.set_tooltip_text(Some("Copy to clipboard (Ctrl+C)"));
+    let save_btn = Button::with_label("Save");
+    save_btn.set_tooltip_text(Some("Save to the screenshot folder (Ctrl+S)"));
+    let save_as_btn = Button::with_label("Save As…");
+    save_as_btn.set_tooltip_text(Some("Choose where and in which format to save (Ctrl+Shift+S)"));
+    let upload_btn = Button::with_label("Upload");
+    upload_btn.set_tooltip_text(Some("Save, upload and copy the link (Ctrl+U)"));
     let cancel_btn = Button::with_label("Cancel");
     let done_btn = Button::with_label("Done");
     done_btn.add_css_class("suggested-action");
 
+    action_box.append(&drag_handle(&state));
+    action_box.append(&copy_btn);
+    action_box.append(&save_btn);
+    action_box.append(&save_as_btn);
+    // Only offered once an endpoint is configured
+    if config.upload.is_configured() {
+        action_box.append(&upload_btn);
+    }
     action_box.append(&cancel_btn);
     action_box.append(&done_btn);
 
@@ -120,45 +142,111 @@ pub fn show_editor(
         window_cancel.close();
     });
 
-    // Done button → render final image → save + clipboard
+    // Save, Save As and Copy keep the editor open
+    let state_save = state.clone();
+    let config_save = config.clone();
+    let window_save = window.clone();
+    let saved_save = last_saved.clone();
+    let save_action = move || {
+        if let Some(path) = save_to_dir(&window_save, &state_save, &config_save, &info) {
+            saved_as(&window_save, &path);
+            post_save(&state_save, &config_save, &info, &path);
+            *saved_save.borrow_mut() = Some(path);
+        }
+    };
+
+    let state_save_as = state.clone();
+    let config_save_as = config.clone();
+    let window_save_as = window.clone();
+    let saved_save_as = last_saved.clone();
+    let save_as_action = move || {
+        let window_saved = window_save_as.clone();
+        let saved = saved_save_as.clone();
+        save_as_dialog(&window_save_as, &state_save_as, &config_save_as, info, move |path| {
+            saved_as(&window_saved, &path);
+            *saved.borrow_mut() = Some(path);
+        });
+    };
+
+    let state_copy = state.clone();
+    let config_copy = config.clone();
+    let window_copy = window.clone();
+    let saved_copy = last_saved.clone();
+    let copy_action = move || {
+        copy(&window_copy, &state_copy, &config_copy, saved_copy.borrow().as_deref());
+    };
+
+    let state_upload = state.clone();
+    let config_upload = config.clone();
+    let window_upload = window.clone();
+    let saved_upload = last_saved.clone();
+    let upload_action = move || {
+        if !config_upload.upload.is_configured() {
+            return;
+        }
+        let Some(path) = save_to_dir(&window_upload, &state_upload, &config_upload, &info) else {
+            return;
+        };
+        saved_as(&window_upload, &path);
+        // Not post_save: with upload.auto on, that would upload it twice
+        let (width, height) = {
+            let st = state_upload.borrow();
+            (st.surface.width(), st.surface.height())
+        };
+        output::hooks::post_save(&config_upload, &info, &path, width, height);
+        let values = output::filename::TemplateValues::new(&config_upload, &info, width, height);
+        let window_uploaded = window_upload.clone();
+        output::upload::upload_in_background(&config_upload, values, &path, move |result| {
+            match result {
+                Ok(_) => window_uploaded.set_title(Some("Razorshot - Annotate (link copied)")),
+                Err(e) => crate::ui::feedback::show_error(
+                    Some(&window_uploaded.clone().upcast()),
+                    "Upload Failed",
+                    &e,
+                ),
+            }
+        });
+        *saved_upload.borrow_mut() = Some(path);
+    };
+
+    let save_btn_action = save_action.clone();
+    save_btn.connect_clicked(move |_| save_btn_action());
+    let save_as_btn_action = save_as_action.clone();
+    save_as_btn.connect_clicked(move |_| save_as_btn_action());
+    let copy_btn_action = copy_action.clone();
+    copy_btn.connect_clicked(move |_| copy_btn_action());
+    let upload_btn_action = upload_action.clone();
+    upload_btn.connect_clicked(move |_| upload_btn_action());
+
+    // Done button → behavior.done_action, then close
     let state_done = state.clone();
     let config_done = config.clone();
     let window_done = window.clone();
-    done_btn.connect_clicked(move |_| {
-        let st = state_done.borrow();
-        match render_final_image(&st) {
-            Ok(final_surface) => {
-                // Save to file
-                match output::file::save_screenshot(&final_surface, &config_done) {
-                    Ok(path) => log::info!("Saved to {}", path.display()),
-                    Err(e) => {
-                        crate::ui::feedback::show_error(
-                            Some(&window_done.clone().upcast()),
-                            "Save Failed",
-                            &e.to_string(),
-                        );
-                    }
-                }
-                // Copy to clipboard
-                if config_done.behavior.copy_to_clipboard {
-                    if let Err(e) = output::clipboard::copy_to_clipboard(&final_surface) {
-                        crate::ui::feedback::show_error(
-                            Some(&window_done.clone().upcast()),
-                            "Clipboard Failed",
-                            &e.to_string(),
-                        );
-                    }
-                }
+    let saved_done = last_saved.clone();
+    done_btn.connect_clicked(move |_| match config_done.behavior.done_action {
+        DoneAction::Save => {
+            let saved = save_to_dir(&window_done, &state_done, &config_done, &info);
+            if config_done.behavior.copy_to_clipboard {
+                copy(&window_done, &state_done, &config_done, saved.as_deref());
             }
-            Err(e) => {
-                crate::ui::feedback::show_error(
-                    Some(&window_done.clone().upcast()),
-                    "Render Failed",
-                    &e.to_string(),
-                );
+            // After copying, so a hook's output can replace the clipboard
+            if let Some(path) = &saved {
+                post_save(&state_done, &config_done, &info, path);
+            }
+            window_done.close();
+        }
+        DoneAction::Copy => {
+            let saved = saved_done.borrow().clone();
+            if copy(&window_done, &state_done, &config_done, saved.as_deref()) {
+                window_done.close();
             }
         }
-        window_done.close();
+        DoneAction::SaveAs => {
+            let window_saved = window_done.clone();
+            save_as_dialog(&window_done, &state_done, &config_done, info, move |_| {
+                window_saved.close();
+            });
+        }
     });
 
     // Keyboard shortcuts
@@ -168,10 +256,32 @@ pub fn show_editor(
     let window_key = window.clone();
     key_ctrl.connect_key_pressed(move |_, keyval, _, modifier| {
         let ctrl = modifier.contains(gdk4::ModifierType::CONTROL_MASK);
+        let shift = modifier.contains(gdk4::ModifierType::SHIFT_MASK);
         if keyval == gdk4::Key::Escape {
             window_key.close();
             return glib::Propagation::Stop;
         }
+        if ctrl && shift && (keyval == gdk4::Key::s || keyval == gdk4::Key::S) {
+            save_as_action();
+            return glib::Propagation::Stop;
+        }
+        if ctrl && keyval == gdk4::Key::s {
+            save_action();
+            return glib::Propagation::Stop;
+        }
+        if ctrl && keyval == gdk4::Key::c {
+            copy_action();
+            return glib::Propagation::Stop;
+        }
+        if ctrl && keyval == gdk4::Key::u {
+            upload_action();
+            return glib::Propagation::Stop;
+        }
+        if ctrl && shift && (keyval == gdk4::Key::z || keyval == gdk4::Key::Z) {
+            state_key.borrow_mut().redo();
+            da_key.queue_draw();
+            return glib::Propagation::Stop;
+        }
         if ctrl && keyval == gdk4::Key::z {
             state_key.borrow_mut().undo();
             da_key.queue_draw();
@@ -182,6 +292,27 @@ pub fn show_editor(
             da_key.queue_draw();
             return glib::Propagation::Stop;
         }
+        // Paste a clipboard image as a movable layer
+        if ctrl && keyval == gdk4::Key::v {
+            let state_paste = state_key.clone();
+            let da_paste = da_key.clone();
+            capture::clipboard::read_image(&window_key.clipboard(), move |result| match result {
+                Ok(surface) => {
+                    state_paste.borrow_mut().paste_image(surface);
+                    da_paste.queue_draw();
+                }
+                Err(e) => log::warn!("Nothing to paste: {}", e),
+            });
+            return glib::Propagation::Stop;
+        }
+        // 1-9 select palette swatches
+        if !ctrl {
+            if let Some(digit) = keyval.to_unicode().and_then(|c| c.to_digit(10)) {
+                if digit >= 1 && state_key.borrow_mut().select_palette(digit as usize - 1) {
+                    return glib::Propagation::Stop;
+                }
+            }
+        }
         glib::Propagation::Proceed
     });
     window.add_controller(key_ctrl);
@@ -189,3 +320,184 @@ pub fn show_editor(
     window.set_child(Some(&main_box));
     window.present();
 }
+
+/// Render the annotated screenshot, reporting a failure on `window`
+fn render(window: &ApplicationWindow, state: &Rc<RefCell<CanvasState>>) -> Option<ImageSurface> {
+    match render_final_image(&state.borrow()) {
+        Ok(surface) => Some(surface),
+        Err(e) => {
+            crate::ui::feedback::show_error(
+                Some(&window.clone().upcast()),
+                "Render Failed",
+                &e.to_string(),
+            );
+            None
+        }
+    }
+}
+
+/// Save to the configured directory. Returns the new file, or None after
+/// reporting the error.
+fn save_to_dir(
+    window: &ApplicationWindow,
+    state: &Rc<RefCell<CanvasState>>,
+    config: &Config,
+    info: &CaptureInfo,
+) -> Option<PathBuf> {
+    let surface = render(window, state)?;
+    match output::file::save_screenshot(&surface, config, info, &state.borrow().shapes) {
+        Ok(path) => {
+            log::info!("Saved to {}", path.display());
+            Some(path)
+        }
+        Err(e) => {
+            crate::ui::feedback::show_error(
+                Some(&window.clone().upcast()),
+                "Save Failed",
+                &e.to_string(),
+            );
+            None
+        }
+    }
+}
+
+/// Copy in the configured clipboard mode. Returns false after reporting an error.
+fn copy(
+    window: &ApplicationWindow,
+    state: &Rc<RefCell<CanvasState>>,
+    config: &Config,
+    saved: Option<&Path>,
+) -> bool {
+    let Some(surface) = render(window, state) else {
+        return false;
+    };
+    match output::clipboard::copy_to_clipboard(&surface, saved, config.behavior.clipboard_mode) {
+        Ok(()) => true,
+        Err(e) => {
+            crate::ui::feedback::show_error(
+                Some(&window.clone().upcast()),
+                "Clipboard Failed",
+                &e.to_string(),
+            );
+            false
+        }
+    }
+}
+
+fn post_save(state: &Rc<RefCell<CanvasState>>, config: &Config, info: &CaptureInfo, path: &Path) {
+    let st = state.borrow();
+    output::hooks::post_save(config, info, path, st.surface.width(), st.surface.height());
+    output::upload::upload_saved(config, info, path, st.surface.width(), st.surface.height());
+}
+
+/// Ask for a file name and save there. The format follows the extension, which
+/// is required: a name without a known one is an error and nothing is saved.
+/// `on_saved` runs after a successful save.
+fn save_as_dialog<F>(
+    window: &ApplicationWindow,
+    state: &Rc<RefCell<CanvasState>>,
+    config: &Config,
+    info: CaptureInfo,
+    on_saved: F,
+) where
+    F: FnOnce(PathBuf) + 'static,
+{
+    let filters = gio::ListStore::new::<gtk4::FileFilter>();
+    let mut default_filter = None;
+    for format in ExportFormat::ALL {
+        let filter = gtk4::FileFilter::new();
+        filter.set_name(Some(&format!("{} image", format.extension().to_uppercase())));
+        filter.add_suffix(format.extension());
+        filters.append(&filter);
+        if format == config.export_format {
+            default_filter = Some(filter);
+        }
+    }
+    let dialog = gtk4::FileDialog::builder()
+        .title("Save Screenshot As")
+        .modal(true)
+        .filters(&filters)
+        .initial_folder(&gio::File::for_path(config.resolve_save_dir()))
+        .initial_name(format!("Screenshot.{}", config.export_format.extension()))
+        .build();
+    dialog.set_default_filter(default_filter.as_ref());
+
+    let window_done = window.clone();
+    let state = state.clone();
+    let config = config.clone();
+    dialog.save(Some(window), None::<&gio::Cancellable>, move |result| {
+        // Dismissing the dialog is reported as an error too
+        let Some(path) = result.ok().and_then(|file| file.path()) else {
+            return;
+        };
+        // The dialog has already asked about replacing exactly this name, and
+        // doesn't say which filter was picked, so don't guess an extension
+        let Some(format) = path
+            .extension()
+            .and_then(|ext| ExportFormat::from_extension(&ext.to_string_lossy()))
+        else {
+            crate::ui::feedback::show_error(
+                Some(&window_done.clone().upcast()),
+                "Save Failed",
+                &format!(
+                    "Add an extension to the name to pick the format, e.g. {}.{}",
+                    path.file_stem().unwrap_or_default().to_string_lossy(),
+                    config.export_format.extension()
+                ),
+            );
+            return;
+        };
+
+        let Some(surface) = render(&window_done, &state) else {
+            return;
+        };
+        let shapes = state.borrow().shapes.clone();
+        match output::file::save_screenshot_as(&surface, &path, format, &config, &info, &shapes) {
+            Ok(()) => {
+                let (width, height) = (surface.width(), surface.height());
+                output::hooks::post_save(&config, &info, &path, width, height);
+                output::upload::upload_saved(&config, &info, &path, width, height);
+                on_saved(path);
+            }
+            Err(e) => crate::ui::feedback::show_error(
+                Some(&window_done.clone().upcast()),
+                "Save Failed",
+                &e.to_string(),
+            ),
+        }
+    });
+}
+
+/// A grip that drags the annotated screenshot into other apps, as a file
+/// and as PNG data
+fn drag_handle(state: &Rc<RefCell<CanvasState>>) -> GtkBox {
+    let handle = GtkBox::new(Orientation::Horizontal, 4);
+    handle.append(&gtk4::Image::from_icon_name("image-x-generic-symbolic"));
+    handle.append(&gtk4::Label::new(Some("Drag")));
+    handle.set_margin_end(8);
+    handle.set_tooltip_text(Some("Drag the screenshot into a browser, chat or file manager"));
+
+    let source = gtk4::DragSource::new();
+    source.set_actions(gdk4::DragAction::COPY);
+    let state = state.clone();
+    source.connect_prepare(move |source, _x, _y| {
+        let surface = render_final_image(&state.borrow())
+            .map_err(|e| log::error!("Failed to render for drag: {}", e))
+            .ok()?;
+        match output::drag::icon(&surface) {
+            Ok(icon) => source.set_icon(Some(&icon), 0, 0),
+            Err(e) => log::warn!("Failed to make drag icon: {}", e),
+        }
+        output::drag::content(&surface)
+            .map_err(|e| log::error!("Failed to prepare drag: {}", e))
+            .ok()
+    });
+    handle.add_controller(source);
+    handle
+}
+
+/// Show the last saved file in the title bar
+fn saved_as(window: &ApplicationWindow, path: &Path) {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    window.set_title(Some(&format!("Razorshot - Annotate ({name} saved)")));
+}